@@ -1,17 +1,38 @@
 /// Settings types for actix-web
 
+pub use crate::address::{Address, Endpoint, Host};
 use crate::core::Parse;
 use crate::error::AtError;
 use regex::Regex;
 use serde::de;
-use serde_derive::Deserialize;
+use serde_derive::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::env;
 use std::fmt;
 use std::path::PathBuf;
+use std::time::Duration;
 
 
-#[derive(Debug, Clone, Deserialize, PartialEq, Eq, Hash)]
+impl_serialize_with_display![
+    SocketMode, SocketOwner, NumWorkers, Backlog, MaxConnections, MaxConnectionRate,
+    KeepAlive, Timeout, TlsVersion, CipherSuite, ReloadInterval,
+];
+
+
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq, Hash)]
 pub struct ActixSettings {
-    pub hosts: Vec<Address>,
+    /// Shorthand for listeners that only have an address.  Those without
+    /// a URL scheme serve `https` iff `ssl.enabled` is `true`.
+    #[serde(default)]
+    pub hosts: Vec<Endpoint>,
+    /// The name of an environment variable e.g. `"PORT"`, whose value
+    /// replaces the port of the TCP `hosts` when set.
+    /// See `ActixSettings::apply_port_from_env()`.
+    #[serde(default, rename = "port-from-env")]
+    pub port_from_env: Option<String>,
+    /// Listeners with their own options, in addition to the `hosts`.
+    #[serde(default)]
+    pub listeners: Vec<Listener>,
     pub mode: Mode,
     #[serde(rename = "enable-compression")]
     pub enable_compression: bool,
@@ -32,74 +53,539 @@ pub struct ActixSettings {
     pub client_shutdown: Timeout,
     #[serde(rename = "shutdown-timeout")]
     pub shutdown_timeout: Timeout,
+    /// Where to write the bound addresses once all listeners are bound.
+    #[serde(default, rename = "port-file")]
+    pub port_file: Option<PathBuf>,
+    /// Privileges to drop once all listeners are bound.
+    #[serde(default)]
+    pub process: ProcessSettings,
     pub ssl: Ssl,
 }
 
-#[derive(Debug, Clone, Deserialize, PartialEq, Eq, Hash)]
-pub struct Address {
-    pub host: String,
-    pub port: u16,
-}
-
-lazy_static::lazy_static! {
-    pub(crate) static ref ADDR_REGEX: Regex = Regex::new(r#"(?x)
-        \[           # opening square bracket
-        (\s)*            # optional whitespace
-            "(?P<host>[^"]+)"   # host name  (string)
-            ,                   # separating comma
-            (\s)*               # optional whitespace
-            (?P<port>\d+)       # port number  (integer)
-        (\s)*            # optional whitespace
-        \]           # closing square bracket
-    "#).expect("Failed to compile regex: ADDR_REGEX");
-    pub(crate) static ref ADDR_LIST_REGEX: Regex = Regex::new(r#"(?x)
-        \[       # opening square bracket (list)
-        (\s)*        # optional whitespace
-            (?P<elements>(
-                \[".*", (\s)* \d+\] # element
-                (,)?                # element separator
-                (\s)*               # optional whitespace
-            )*)
-        (\s)*        # optional whitespace
-        \]       # closing square bracket (list)
-    "#).expect("Failed to compile regex: ADDRS_REGEX");
-}
-
-impl Parse for Address {
-    fn parse(string: &str) -> Result<Self, AtError> {
-        let mut items = string.trim()
-            .trim_start_matches("[")
-            .trim_end_matches("]")
-            .split(",");
-        let parse_error = || AtError::ParseAddressError(string.to_string());
-        if !ADDR_REGEX.is_match(string) { return Err(parse_error()); }
-        Ok(Self {
-            host: items.next().ok_or(parse_error())?.trim().to_string(),
-            port: items.next().ok_or(parse_error())?.trim().parse()?
-        })
+impl ActixSettings {
+    /// If `port_from_env` names an environment variable that is set, use
+    /// its value as the port of every TCP entry in `hosts`, keeping their
+    /// hosts and schemes.  Without any TCP entries, `0.0.0.0` is added with
+    /// that port.  The `listeners` aren't affected, and neither are the
+    /// `hosts` if the variable isn't set.
+    pub fn apply_port_from_env(&mut self) -> Result<(), AtError> {
+        let name = match &self.port_from_env {
+            Some(name) => name,
+            None => return Ok(()),
+        };
+        let invalid = |value: String| AtError::InvalidEnvVar {
+            name: name.clone(),
+            value,
+            expected: "a port number from 0 to 65535",
+        };
+        let port: u16 = match env::var(name) {
+            Err(env::VarError::NotPresent) => return Ok(()),
+            Err(env::VarError::NotUnicode(value)) =>
+                return Err(invalid(value.to_string_lossy().into_owned())),
+            Ok(value) => value.trim().parse().map_err(|_| invalid(value))?,
+        };
+        let mut hosts: Vec<Endpoint> = Vec::with_capacity(self.hosts.len() + 1);
+        for mut endpoint in self.hosts.drain(..) {
+            if let Endpoint::Address(address)
+                | Endpoint::Http(address)
+                | Endpoint::Https(address) = &mut endpoint {
+                address.port = port;
+            }
+            // Entries that only differed in their ports would clash now:
+            if !hosts.contains(&endpoint) {
+                hosts.push(endpoint);
+            }
+        }
+        if hosts.iter().all(|endpoint| matches!(endpoint, Endpoint::Unix(_))) {
+            let host = Host::Ip([0, 0, 0, 0].into());
+            hosts.push(Endpoint::Address(Address { host, port }));
+        }
+        self.hosts = hosts;
+        Ok(())
+    }
+
+    /// Every listener to bind: The `hosts` shorthand, followed by the
+    /// `listeners` table.
+    pub fn all_listeners(&self) -> Vec<Listener> {
+        self.hosts.iter()
+            .map(|endpoint| Listener {
+                tls: match endpoint {
+                    Endpoint::Address(_) => self.ssl.enabled,
+                    Endpoint::Https(_) => true,
+                    Endpoint::Http(_) | Endpoint::Unix(_) => false,
+                },
+                ..Listener::new(match endpoint {
+                    Endpoint::Address(address)
+                    | Endpoint::Http(address)
+                    | Endpoint::Https(address) => ListenAddress::Tcp(address.clone()),
+                    Endpoint::Unix(path) => ListenAddress::Unix(path.clone()),
+                })
+            })
+            .chain(self.listeners.iter().cloned())
+            .collect()
+    }
+}
+
+/// An address to listen on, with options of its own.
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq, Hash)]
+pub struct Listener {
+    #[serde(flatten)]
+    pub address: ListenAddress,
+    /// Whether to serve `https` rather than `http`, as configured by the
+    /// `Ssl` settings.  `Ssl::enabled` doesn't apply to listeners.
+    #[serde(default)]
+    pub tls: bool,
+    /// Replaces `Ssl::certificate` for this listener.  Requires `private_key`.
+    #[serde(default)]
+    pub certificate: Option<PathBuf>,
+    /// Replaces `Ssl::private_key` for this listener.  Requires `certificate`.
+    #[serde(default, rename = "private-key")]
+    pub private_key: Option<PathBuf>,
+    /// Replaces `Ssl::private_key_passphrase` for this listener.
+    #[serde(default, rename = "private-key-passphrase")]
+    pub private_key_passphrase: Option<Secret>,
+    /// Replaces `Ssl::pkcs12` for this listener.
+    #[serde(default)]
+    pub pkcs12: Option<PathBuf>,
+    /// Replaces `ActixSettings::backlog` for this TCP listener.
+    #[serde(default)]
+    pub backlog: Backlog,
+    /// Which of the addresses that the host of a TCP listener resolves to
+    /// are bound.
+    #[serde(default)]
+    pub resolve: Resolve,
+    /// The permissions of the socket file of a Unix listener.
+    #[serde(default)]
+    pub mode: Option<SocketMode>,
+    /// The owner of the socket file of a Unix listener.
+    #[serde(default)]
+    pub owner: Option<SocketOwner>,
+    /// What to do with an existing socket file when binding a Unix listener.
+    #[serde(default, rename = "stale-socket")]
+    pub stale_socket: StaleSocket,
+    /// The name of the inherited socket(s) used by a socket-activated
+    /// listener, as passed in `LISTEN_FDNAMES`.  Without a name, the listener
+    /// uses the inherited sockets that no other listener names.
+    #[serde(default, rename = "fd-name")]
+    pub fd_name: Option<String>,
+    /// Options set on the sockets of a TCP listener before binding them.
+    #[serde(flatten)]
+    pub socket: SocketOptions,
+}
+
+impl Listener {
+    /// A plain `http` listener at `address`, with all options at their
+    /// defaults.
+    pub fn new(address: ListenAddress) -> Self {
+        Self {
+            address,
+            tls: false,
+            certificate: None,
+            private_key: None,
+            private_key_passphrase: None,
+            pkcs12: None,
+            backlog: Backlog::Default,
+            resolve: Resolve::All,
+            mode: None,
+            owner: None,
+            stale_socket: StaleSocket::Remove,
+            fd_name: None,
+            socket: SocketOptions::default(),
+        }
+    }
+
+    /// Check that the options of `self` apply to its kind of address.
+    pub fn validate(&self) -> Result<(), AtError> {
+        match self.address {
+            ListenAddress::Tcp(_) | ListenAddress::Activation(_)
+                if self.mode.is_some() || self.owner.is_some() =>
+                Err(InvalidValue! {
+                    expected: "`mode` and `owner` only for `unix` listeners",
+                    got: &self.address,
+                }),
+            ListenAddress::Unix(_) if self.tls => Err(InvalidValue! {
+                expected: "`tls = false` for `unix` listeners \
+                           (actix-web doesn't support TLS over Unix sockets)",
+                got: &self.address,
+            }),
+            ListenAddress::Unix(_) | ListenAddress::Activation(_)
+                if self.backlog != Backlog::Default =>
+                Err(InvalidValue! {
+                    expected: "`backlog` only for TCP listeners \
+                               (inherited sockets are already listening)",
+                    got: &self.address,
+                }),
+            ListenAddress::Tcp(_) | ListenAddress::Unix(_) if self.fd_name.is_some() =>
+                Err(InvalidValue! {
+                    expected: "`fd-name` only for socket-activated listeners",
+                    got: &self.address,
+                }),
+            ListenAddress::Unix(_) | ListenAddress::Activation(_)
+                if self.resolve != Resolve::default() =>
+                Err(InvalidValue! {
+                    expected: "`resolve` only for TCP listeners",
+                    got: &self.address,
+                }),
+            ListenAddress::Unix(_) | ListenAddress::Activation(_)
+                if self.socket != SocketOptions::default() =>
+                Err(InvalidValue! {
+                    expected: "socket options only for TCP listeners \
+                               (inherited sockets are already bound)",
+                    got: &self.address,
+                }),
+            ListenAddress::Tcp(_) if [&self.socket.keepalive_idle, &self.socket.keepalive_interval]
+                .iter().any(|timeout| matches!(timeout, Timeout::Milliseconds(_))) =>
+                Err(InvalidValue! {
+                    expected: "`keepalive-idle` and `keepalive-interval` in seconds",
+                    got: &self.address,
+                }),
+            _ => Ok(()),
+        }
+    }
+
+    /// The `Ssl` settings for this listener: `ssl`, except that the default
+    /// certificate is replaced by the one configured for this listener (if
+    /// any).
+    pub fn ssl(&self, ssl: &Ssl) -> Result<Ssl, AtError> {
+        let mut ssl = ssl.clone();
+        match (&self.certificate, &self.private_key, &self.pkcs12) {
+            (None, None, None) => return Ok(ssl),
+            (Some(certificate), Some(private_key), None) => {
+                ssl.certificate = certificate.clone();
+                ssl.private_key = private_key.clone();
+                ssl.pkcs12 = None;
+            },
+            (None, None, Some(pkcs12)) => ssl.pkcs12 = Some(pkcs12.clone()),
+            _ => return Err(InvalidValue! {
+                expected: "either both `certificate` and `private-key`, or `pkcs12`",
+                got: format!("{:?}", self),
+            }),
+        }
+        ssl.private_key_passphrase = self.private_key_passphrase.clone();
+        Ok(ssl)
     }
 }
 
-impl Parse for Vec<Address> {
+impl Parse for Vec<Listener> {
+    /// Parse a `TOML` array of inline tables
+    /// e.g. `[{ address = ["0.0.0.0", 8443], tls = true }]`.
     fn parse(string: &str) -> Result<Self, AtError> {
-        let parse_error = || AtError::ParseAddressError(string.to_string());
-        if !ADDR_LIST_REGEX.is_match(string) { return Err(parse_error()); }
-        let mut addrs = vec![];
-        for list_caps in ADDR_LIST_REGEX.captures_iter(string) {
-            let elements = &list_caps["elements"].trim();
-            for elt_caps in ADDR_REGEX.captures_iter(elements) {
-                addrs.push(Address {
-                    host: elt_caps["host"].to_string(),
-                    port: elt_caps["port"].parse()?
-                });
+        crate::core::parse_toml_value(string)
+    }
+}
+
+/// Where a `Listener` accepts connections.  In `TOML`, this is either an
+/// `address` key in the same format as the `hosts` entries, a `unix` key
+/// holding the path of a Unix domain socket, or an `activation` key naming
+/// the service manager that passes the listening sockets to this process.
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq, Hash)]
+pub enum ListenAddress {
+    #[serde(rename = "address")]
+    Tcp(Address),
+    #[serde(rename = "unix")]
+    Unix(PathBuf),
+    #[serde(rename = "activation")]
+    Activation(Activation),
+}
+
+impl fmt::Display for ListenAddress {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Tcp(address) => write!(f, "{}", address),
+            Self::Unix(path) => write!(f, "unix:{}", path.display()),
+            Self::Activation(Activation::Systemd) => write!(f, "systemd activation"),
+        }
+    }
+}
+
+impl From<Address> for ListenAddress {
+    fn from(address: Address) -> Self { Self::Tcp(address) }
+}
+
+/// How the listening sockets of a socket-activated `Listener` are passed to
+/// this process.
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, PartialEq, Eq, Hash)]
+pub enum Activation {
+    /// Through the `LISTEN_PID`, `LISTEN_FDS` and `LISTEN_FDNAMES`
+    /// environment variables, as done by systemd.
+    #[serde(rename = "systemd")]
+    Systemd,
+}
+
+impl Parse for Activation {
+    fn parse(string: &str) -> std::result::Result<Self, AtError> {
+        match string {
+            "systemd" => Ok(Self::Systemd),
+            _ => Err(InvalidValue! {
+                expected: "\"systemd\".",
+                got: string,
+            })
+        }
+    }
+}
+
+/// The permission bits of a Unix socket file, written as an octal string
+/// e.g. `"0660"`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SocketMode(pub u32);
+
+impl fmt::Display for SocketMode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:04o}", self.0)
+    }
+}
+
+impl Parse for SocketMode {
+    fn parse(string: &str) -> std::result::Result<Self, AtError> {
+        let digits = string.strip_prefix("0o").unwrap_or(string);
+        match u32::from_str_radix(digits, 8) {
+            Ok(mode) if mode <= 0o777 => Ok(Self(mode)),
+            _ => Err(InvalidValue! {
+                expected: "an octal permission mode <= \"0777\" e.g. \"0660\"",
+                got: string,
+            }),
+        }
+    }
+}
+
+impl Parse for Option<SocketMode> {
+    /// Parse a socket mode, where an empty `string` means no mode at all.
+    fn parse(string: &str) -> std::result::Result<Self, AtError> {
+        match string {
+            "" => Ok(None),
+            string => SocketMode::parse(string).map(Some),
+        }
+    }
+}
+
+impl<'de> serde::Deserialize<'de> for SocketMode {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where D: serde::Deserializer<'de> {
+        struct SocketModeVisitor;
+
+        impl<'de> de::Visitor<'de> for SocketModeVisitor {
+            type Value = SocketMode;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("a string containing an octal permission mode e.g. \"0660\"")
+            }
+
+            fn visit_str<E>(self, value: &str) -> Result<Self::Value, E>
+            where E: de::Error {
+                match SocketMode::parse(value) {
+                    Ok(mode) => Ok(mode),
+                    Err(AtError::InvalidValue { expected, got, .. }) =>
+                        Err(de::Error::invalid_value(de::Unexpected::Str(&got), &expected)),
+                    Err(_) => unreachable!(),
+                }
             }
         }
-        Ok(addrs)
+
+        deserializer.deserialize_string(SocketModeVisitor)
     }
 }
 
+/// The owner of a Unix socket file, written as `"user"`, `"user:group"` or
+/// `":group"`.  Users and groups are given by either name or numeric id.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct SocketOwner {
+    pub user: Option<String>,
+    pub group: Option<String>,
+}
 
-#[derive(Debug, Clone, Deserialize, PartialEq, Eq, Hash)]
+impl fmt::Display for SocketOwner {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match (&self.user, &self.group) {
+            (Some(user), Some(group)) => write!(f, "{}:{}", user, group),
+            (Some(user), None) => write!(f, "{}", user),
+            (None, Some(group)) => write!(f, ":{}", group),
+            (None, None) => Ok(()),
+        }
+    }
+}
+
+impl Parse for SocketOwner {
+    fn parse(string: &str) -> std::result::Result<Self, AtError> {
+        let (user, group) = match string.split_once(':') {
+            Some((user, group)) => (user, Some(group)),
+            None => (string, None),
+        };
+        let non_empty = |name: &str| Some(name.to_string()).filter(|name| !name.is_empty());
+        match (non_empty(user), group.map(non_empty)) {
+            (None, None) | (_, Some(None)) => Err(InvalidValue! {
+                expected: "\"user\", \"user:group\" or \":group\"",
+                got: string,
+            }),
+            (user, group) => Ok(Self { user, group: group.flatten() }),
+        }
+    }
+}
+
+impl Parse for Option<SocketOwner> {
+    /// Parse a socket owner, where an empty `string` means no owner at all.
+    fn parse(string: &str) -> std::result::Result<Self, AtError> {
+        match string {
+            "" => Ok(None),
+            string => SocketOwner::parse(string).map(Some),
+        }
+    }
+}
+
+impl<'de> serde::Deserialize<'de> for SocketOwner {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where D: serde::Deserializer<'de> {
+        struct SocketOwnerVisitor;
+
+        impl<'de> de::Visitor<'de> for SocketOwnerVisitor {
+            type Value = SocketOwner;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("a string of the form \"user\", \"user:group\" or \":group\"")
+            }
+
+            fn visit_str<E>(self, value: &str) -> Result<Self::Value, E>
+            where E: de::Error {
+                match SocketOwner::parse(value) {
+                    Ok(owner) => Ok(owner),
+                    Err(AtError::InvalidValue { expected, got, .. }) =>
+                        Err(de::Error::invalid_value(de::Unexpected::Str(&got), &expected)),
+                    Err(_) => unreachable!(),
+                }
+            }
+        }
+
+        deserializer.deserialize_string(SocketOwnerVisitor)
+    }
+}
+
+/// What to do with a socket file that already exists when binding a Unix
+/// listener.  Files that aren't sockets are never removed.
+#[derive(Debug, Clone, Copy, Default, Deserialize, Serialize, PartialEq, Eq, Hash)]
+pub enum StaleSocket {
+    /// Remove the socket file, unless another process accepts connections
+    /// on it.
+    #[default]
+    #[serde(rename = "remove")]
+    Remove,
+    /// Always remove the socket file, even if it's in use.
+    #[serde(rename = "force")]
+    Force,
+    /// Never remove the socket file, so that binding fails if it exists.
+    #[serde(rename = "keep")]
+    Keep,
+}
+
+impl Parse for StaleSocket {
+    fn parse(string: &str) -> std::result::Result<Self, AtError> {
+        match string {
+            "remove" => Ok(Self::Remove),
+            "force"  => Ok(Self::Force),
+            "keep"   => Ok(Self::Keep),
+            _ => Err(InvalidValue! {
+                expected: "\"remove\" | \"force\" | \"keep\".",
+                got: string,
+            })
+        }
+    }
+}
+
+/// Which of the addresses that the host of a TCP `Listener` resolves to are
+/// bound.  Addresses with an IP rather than a host name resolve to just that.
+#[derive(Debug, Clone, Copy, Default, Deserialize, Serialize, PartialEq, Eq, Hash)]
+pub enum Resolve {
+    /// Every address.  Binding succeeds if any of them can be bound.
+    #[default]
+    #[serde(rename = "all")]
+    All,
+    /// Only the first address returned by the resolver.
+    #[serde(rename = "first")]
+    First,
+    /// Every IPv4 address.
+    #[serde(rename = "ipv4")]
+    Ipv4,
+    /// Every IPv6 address.
+    #[serde(rename = "ipv6")]
+    Ipv6,
+}
+
+impl Parse for Resolve {
+    fn parse(string: &str) -> std::result::Result<Self, AtError> {
+        match string {
+            "all"   => Ok(Self::All),
+            "first" => Ok(Self::First),
+            "ipv4"  => Ok(Self::Ipv4),
+            "ipv6"  => Ok(Self::Ipv6),
+            _ => Err(InvalidValue! {
+                expected: "\"all\" | \"first\" | \"ipv4\" | \"ipv6\".",
+                got: string,
+            })
+        }
+    }
+}
+
+/// Options set on the sockets of a TCP `Listener` before binding them.  The
+/// OS defaults apply to the options that aren't set.
+#[derive(Debug, Clone, Default, Deserialize, Serialize, PartialEq, Eq, Hash)]
+pub struct SocketOptions {
+    /// `SO_REUSEPORT`: Whether other sockets can bind the same address and
+    /// port, with the OS distributing the connections among them.
+    #[serde(default, rename = "reuse-port")]
+    pub reuse_port: Option<bool>,
+    /// `SO_REUSEADDR`: Whether the address can be bound while connections
+    /// to a previous socket are in `TIME_WAIT`.  Enabled on Unix by default,
+    /// like for the sockets bound by the standard library.
+    #[serde(default, rename = "reuse-address")]
+    pub reuse_address: Option<bool>,
+    /// `TCP_NODELAY`: Whether accepted connections disable Nagle's algorithm.
+    #[serde(default)]
+    pub nodelay: Option<bool>,
+    /// `IPV6_V6ONLY`: Whether an IPv6 socket rejects IPv4 connections.  Has
+    /// no effect on IPv4 addresses.
+    #[serde(default, rename = "ipv6-only")]
+    pub ipv6_only: Option<bool>,
+    /// `TCP_KEEPIDLE`: How long a connection is idle before keepalive probes
+    /// are sent.  Setting any of the keepalive options enables `SO_KEEPALIVE`.
+    #[serde(default, rename = "keepalive-idle")]
+    pub keepalive_idle: Timeout,
+    /// `TCP_KEEPINTVL`: The time between keepalive probes.
+    #[serde(default, rename = "keepalive-interval")]
+    pub keepalive_interval: Timeout,
+    /// `TCP_KEEPCNT`: How many unanswered keepalive probes close a connection.
+    #[serde(default, rename = "keepalive-count")]
+    pub keepalive_count: Option<u32>,
+    /// `SO_SNDBUF`: The size of the send buffer in bytes.
+    #[serde(default, rename = "send-buffer-size")]
+    pub send_buffer_size: Option<usize>,
+    /// `SO_RCVBUF`: The size of the receive buffer in bytes.
+    #[serde(default, rename = "recv-buffer-size")]
+    pub recv_buffer_size: Option<usize>,
+}
+
+
+/// The identity that `BasicSettings::drop_privileges()` switches to once all
+/// listeners are bound, e.g. to bind ports below 1024 as root and then serve
+/// requests as an unprivileged user.  Only supported on Unix.
+///
+/// Files that are reloaded after dropping privileges (see `ReloadInterval`)
+/// are read as `user`, and their paths are resolved inside `chroot`.  A
+/// reload of files that `user` can't read, or that don't exist inside
+/// `chroot`, fails and keeps the current certificates in use.
+#[derive(Debug, Clone, Default, Deserialize, Serialize, PartialEq, Eq, Hash)]
+pub struct ProcessSettings {
+    /// The name or numeric id of the user to switch to.
+    #[serde(default)]
+    pub user: Option<String>,
+    /// The name or numeric id of the group to switch to.  Defaults to the
+    /// primary group of `user`.
+    #[serde(default)]
+    pub group: Option<String>,
+    /// The directory to change the root directory to, before switching the
+    /// user and group.
+    #[serde(default)]
+    pub chroot: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq, Hash)]
 pub enum Mode {
     #[serde(rename = "development")]
     Development,
@@ -127,6 +613,15 @@ pub enum NumWorkers {
     Manual(usize),
 }
 
+impl fmt::Display for NumWorkers {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Default => write!(f, "default"),
+            Self::Manual(n) => write!(f, "{}", n),
+        }
+    }
+}
+
 impl Parse for NumWorkers {
     fn parse(string: &str) -> std::result::Result<Self, AtError> {
         match string {
@@ -174,12 +669,22 @@ impl<'de> serde::Deserialize<'de> for NumWorkers {
 }
 
 
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
 pub enum Backlog {
+    #[default]
     Default,
     Manual(usize),
 }
 
+impl fmt::Display for Backlog {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Default => write!(f, "default"),
+            Self::Manual(n) => write!(f, "{}", n),
+        }
+    }
+}
+
 impl Parse for Backlog {
     fn parse(string: &str) -> std::result::Result<Self, AtError> {
         match string {
@@ -233,6 +738,15 @@ pub enum MaxConnections {
     Manual(usize),
 }
 
+impl fmt::Display for MaxConnections {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Default => write!(f, "default"),
+            Self::Manual(n) => write!(f, "{}", n),
+        }
+    }
+}
+
 impl Parse for MaxConnections {
     fn parse(string: &str) -> std::result::Result<Self, AtError> {
         match string {
@@ -286,6 +800,15 @@ pub enum MaxConnectionRate {
     Manual(usize),
 }
 
+impl fmt::Display for MaxConnectionRate {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Default => write!(f, "default"),
+            Self::Manual(n) => write!(f, "{}", n),
+        }
+    }
+}
+
 impl Parse for MaxConnectionRate {
     fn parse(string: &str) -> std::result::Result<Self, AtError> {
         match string {
@@ -341,6 +864,17 @@ pub enum KeepAlive {
     Seconds(usize),
 }
 
+impl fmt::Display for KeepAlive {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Default => write!(f, "default"),
+            Self::Disabled => write!(f, "disabled"),
+            Self::Os => write!(f, "os"),
+            Self::Seconds(n) => write!(f, "{} seconds", n),
+        }
+    }
+}
+
 impl Parse for KeepAlive {
     fn parse(string: &str) -> std::result::Result<Self, AtError> {
         lazy_static::lazy_static! {
@@ -406,13 +940,24 @@ impl<'de> serde::Deserialize<'de> for KeepAlive {
 }
 
 
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
 pub enum Timeout {
+    #[default]
     Default,
     Milliseconds(usize),
     Seconds(usize),
 }
 
+impl fmt::Display for Timeout {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Default => write!(f, "default"),
+            Self::Milliseconds(n) => write!(f, "{} milliseconds", n),
+            Self::Seconds(n) => write!(f, "{} seconds", n),
+        }
+    }
+}
+
 impl Parse for Timeout {
     fn parse(string: &str) -> std::result::Result<Self, AtError> {
         lazy_static::lazy_static! {
@@ -483,10 +1028,511 @@ impl<'de> serde::Deserialize<'de> for Timeout {
 }
 
 
-#[derive(Debug, Clone, Deserialize, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq, Hash)]
 pub struct Ssl {
     pub enabled: bool,
+    #[serde(default)]
+    pub backend: TlsBackend,
+    #[serde(default)]
+    pub certificate: PathBuf,
+    #[serde(default, rename = "private-key")]
+    pub private_key: PathBuf,
+    /// The passphrase that `private_key` (or `pkcs12`) is encrypted with.
+    #[serde(default, rename = "private-key-passphrase")]
+    pub private_key_passphrase: Option<Secret>,
+    /// A PKCS#12 bundle holding both the certificate chain and its private
+    /// key.  If present, it's used instead of `certificate` and `private_key`.
+    #[serde(default)]
+    pub pkcs12: Option<PathBuf>,
+    /// Whether to generate a self-signed certificate for the `hosts` when
+    /// neither `certificate` nor `private_key` exists.  Only allowed in
+    /// `Mode::Development`.
+    #[serde(default, rename = "auto-generate")]
+    pub auto_generate: bool,
+    #[serde(default, rename = "client-auth")]
+    pub client_auth: ClientAuth,
+    /// Additional certificates that are selected by SNI during the TLS
+    /// handshake.  Clients asking for any other server name (or none at
+    /// all) are served `certificate`.
+    #[serde(default)]
+    pub certificates: Vec<SniCertificate>,
+    /// How often the certificate, private key and client CA certificate
+    /// files are checked for changes.  Changed files are reloaded and used
+    /// for new connections.
+    #[serde(default, rename = "reload-interval")]
+    pub reload_interval: ReloadInterval,
+    /// The lowest protocol version to accept.  Defaults to the lowest
+    /// version supported by the backend.
+    #[serde(default, rename = "min-version")]
+    pub min_version: Option<TlsVersion>,
+    /// The highest protocol version to accept.  Defaults to the highest
+    /// version supported by the backend.
+    #[serde(default, rename = "max-version")]
+    pub max_version: Option<TlsVersion>,
+    /// The cipher suites to accept.  If empty, the backend's defaults
+    /// are used.
+    #[serde(default, rename = "cipher-suites")]
+    pub cipher_suites: Vec<CipherSuite>,
+    /// The ALPN protocols to offer, in order of preference.  If empty,
+    /// actix-web's defaults are used.
+    #[serde(default)]
+    pub alpn: Vec<String>,
+}
+
+impl Ssl {
+    /// Check that no server name is claimed by more than 1 of the
+    /// `certificates` entries.  Server names are compared case-insensitively.
+    pub fn validate(&self) -> Result<(), AtError> {
+        let mut seen = HashSet::new();
+        for name in self.certificates.iter().flat_map(|cert| &cert.server_names) {
+            if !seen.insert(name.to_ascii_lowercase()) {
+                return Err(AtError::DuplicateServerName(name.clone()));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum TlsVersion {
+    Tls1_2,
+    Tls1_3,
+}
+
+impl fmt::Display for TlsVersion {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Tls1_2 => write!(f, "1.2"),
+            Self::Tls1_3 => write!(f, "1.3"),
+        }
+    }
+}
+
+impl Parse for TlsVersion {
+    fn parse(string: &str) -> std::result::Result<Self, AtError> {
+        match string {
+            "1.2" => Ok(Self::Tls1_2),
+            "1.3" => Ok(Self::Tls1_3),
+            _ => Err(InvalidValue! {
+                expected: "\"1.2\" | \"1.3\".",
+                got: string,
+            })
+        }
+    }
+}
+
+impl Parse for Option<TlsVersion> {
+    /// Parse a TLS version, where an empty `string` means no version at all,
+    /// i.e. the default of the TLS backend.
+    fn parse(string: &str) -> std::result::Result<Self, AtError> {
+        match string {
+            "" => Ok(None),
+            string => TlsVersion::parse(string).map(Some),
+        }
+    }
+}
+
+impl<'de> serde::Deserialize<'de> for TlsVersion {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where D: serde::Deserializer<'de> {
+        struct TlsVersionVisitor;
+
+        impl<'de> de::Visitor<'de> for TlsVersionVisitor {
+            type Value = TlsVersion;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("Either \"1.2\" or \"1.3\"")
+            }
+
+            fn visit_str<E>(self, value: &str) -> Result<Self::Value, E>
+            where E: de::Error {
+                match TlsVersion::parse(value) {
+                    Ok(tls_version) => Ok(tls_version),
+                    Err(AtError::InvalidValue { expected, got, .. }) =>
+                        Err(de::Error::invalid_value(
+                            de::Unexpected::Str(&got),
+                            &expected
+                        )),
+                    Err(_) => unreachable!(),
+                }
+            }
+        }
+
+        deserializer.deserialize_string(TlsVersionVisitor)
+    }
+}
+
+
+/// The cipher suites supported by both TLS backends, named as in the
+/// IANA TLS Cipher Suites registry.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CipherSuite {
+    TlsAes128GcmSha256,
+    TlsAes256GcmSha384,
+    TlsChacha20Poly1305Sha256,
+    TlsEcdheEcdsaWithAes128GcmSha256,
+    TlsEcdheEcdsaWithAes256GcmSha384,
+    TlsEcdheEcdsaWithChacha20Poly1305Sha256,
+    TlsEcdheRsaWithAes128GcmSha256,
+    TlsEcdheRsaWithAes256GcmSha384,
+    TlsEcdheRsaWithChacha20Poly1305Sha256,
+}
+
+impl fmt::Display for CipherSuite {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+impl CipherSuite {
+    pub const ALL: [Self; 9] = [
+        Self::TlsAes128GcmSha256,
+        Self::TlsAes256GcmSha384,
+        Self::TlsChacha20Poly1305Sha256,
+        Self::TlsEcdheEcdsaWithAes128GcmSha256,
+        Self::TlsEcdheEcdsaWithAes256GcmSha384,
+        Self::TlsEcdheEcdsaWithChacha20Poly1305Sha256,
+        Self::TlsEcdheRsaWithAes128GcmSha256,
+        Self::TlsEcdheRsaWithAes256GcmSha384,
+        Self::TlsEcdheRsaWithChacha20Poly1305Sha256,
+    ];
+
+    /// The IANA name of `self`.
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::TlsAes128GcmSha256 => "TLS_AES_128_GCM_SHA256",
+            Self::TlsAes256GcmSha384 => "TLS_AES_256_GCM_SHA384",
+            Self::TlsChacha20Poly1305Sha256 => "TLS_CHACHA20_POLY1305_SHA256",
+            Self::TlsEcdheEcdsaWithAes128GcmSha256 =>
+                "TLS_ECDHE_ECDSA_WITH_AES_128_GCM_SHA256",
+            Self::TlsEcdheEcdsaWithAes256GcmSha384 =>
+                "TLS_ECDHE_ECDSA_WITH_AES_256_GCM_SHA384",
+            Self::TlsEcdheEcdsaWithChacha20Poly1305Sha256 =>
+                "TLS_ECDHE_ECDSA_WITH_CHACHA20_POLY1305_SHA256",
+            Self::TlsEcdheRsaWithAes128GcmSha256 =>
+                "TLS_ECDHE_RSA_WITH_AES_128_GCM_SHA256",
+            Self::TlsEcdheRsaWithAes256GcmSha384 =>
+                "TLS_ECDHE_RSA_WITH_AES_256_GCM_SHA384",
+            Self::TlsEcdheRsaWithChacha20Poly1305Sha256 =>
+                "TLS_ECDHE_RSA_WITH_CHACHA20_POLY1305_SHA256",
+        }
+    }
+
+    /// The protocol version that `self` can be used with.
+    pub fn version(self) -> TlsVersion {
+        match self {
+            Self::TlsAes128GcmSha256
+                | Self::TlsAes256GcmSha384
+                | Self::TlsChacha20Poly1305Sha256 => TlsVersion::Tls1_3,
+            _ => TlsVersion::Tls1_2,
+        }
+    }
+}
+
+impl Parse for CipherSuite {
+    fn parse(string: &str) -> std::result::Result<Self, AtError> {
+        lazy_static::lazy_static! {
+            pub static ref EXPECTED: String = format!(
+                "one of {}",
+                CipherSuite::ALL.iter()
+                    .copied()
+                    .map(CipherSuite::name)
+                    .collect::<Vec<_>>()
+                    .join(", "),
+            );
+        }
+        Self::ALL.iter()
+            .copied()
+            .find(|suite| suite.name() == string)
+            .ok_or_else(|| InvalidValue! {
+                expected: EXPECTED.as_str(),
+                got: string,
+            })
+    }
+}
+
+impl Parse for Vec<CipherSuite> {
+    /// Parse a `TOML` array of cipher suite names.
+    fn parse(string: &str) -> std::result::Result<Self, AtError> {
+        Vec::<String>::parse(string)?.iter()
+            .map(|name| CipherSuite::parse(name))
+            .collect()
+    }
+}
+
+impl<'de> serde::Deserialize<'de> for CipherSuite {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where D: serde::Deserializer<'de> {
+        struct CipherSuiteVisitor;
+
+        impl<'de> de::Visitor<'de> for CipherSuiteVisitor {
+            type Value = CipherSuite;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("the IANA name of a supported cipher suite")
+            }
+
+            fn visit_str<E>(self, value: &str) -> Result<Self::Value, E>
+            where E: de::Error {
+                match CipherSuite::parse(value) {
+                    Ok(cipher_suite) => Ok(cipher_suite),
+                    Err(AtError::InvalidValue { expected, got, .. }) =>
+                        Err(de::Error::invalid_value(
+                            de::Unexpected::Str(&got),
+                            &expected
+                        )),
+                    Err(_) => unreachable!(),
+                }
+            }
+        }
+
+        deserializer.deserialize_string(CipherSuiteVisitor)
+    }
+}
+
+
+/// How often the files of an `Ssl` table are checked for changes.
+///
+/// Reloading happens after `BasicSettings::drop_privileges()`, so when
+/// `[actix.process]` sets a `user` or `chroot`, the files must be readable
+/// by that user at the same paths inside the `chroot` directory.
+/// Otherwise each reload fails, and the current certificates stay in use.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub enum ReloadInterval {
+    #[default]
+    Disabled,
+    Milliseconds(usize),
+    Seconds(usize),
+}
+
+impl fmt::Display for ReloadInterval {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Disabled => write!(f, "disabled"),
+            Self::Milliseconds(n) => write!(f, "{} milliseconds", n),
+            Self::Seconds(n) => write!(f, "{} seconds", n),
+        }
+    }
+}
+
+impl ReloadInterval {
+    /// Returns `None` if reloading is disabled.
+    pub fn as_duration(&self) -> Option<Duration> {
+        match *self {
+            Self::Disabled        => None,
+            Self::Milliseconds(n) => Some(Duration::from_millis(n as u64)),
+            Self::Seconds(n)      => Some(Duration::from_secs(n as u64)),
+        }
+    }
+}
+
+impl Parse for ReloadInterval {
+    fn parse(string: &str) -> std::result::Result<Self, AtError> {
+        match (string, Timeout::parse(string)) {
+            ("disabled", _) => Ok(Self::Disabled),
+            (_, Ok(Timeout::Milliseconds(n))) if n > 0 => Ok(Self::Milliseconds(n)),
+            (_, Ok(Timeout::Seconds(n))) if n > 0 => Ok(Self::Seconds(n)),
+            _ => Err(InvalidValue! {
+                expected: "Either \"disabled\", or a string of the format \"N seconds\" or \"N milliseconds\" where N is an integer > 0",
+                got: string,
+            }),
+        }
+    }
+}
+
+impl<'de> serde::Deserialize<'de> for ReloadInterval {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where D: serde::Deserializer<'de> {
+        struct ReloadIntervalVisitor;
+
+        impl<'de> de::Visitor<'de> for ReloadIntervalVisitor {
+            type Value = ReloadInterval;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                let msg = "Either \"disabled\", or a string of the format \"N seconds\" or \"N milliseconds\" where N is an integer > 0";
+                formatter.write_str(msg)
+            }
+
+            fn visit_str<E>(self, value: &str) -> Result<Self::Value, E>
+            where E: de::Error {
+                match ReloadInterval::parse(value) {
+                    Ok(reload_interval) => Ok(reload_interval),
+                    Err(AtError::InvalidValue { expected, got, .. }) =>
+                        Err(de::Error::invalid_value(
+                            de::Unexpected::Str(&got),
+                            &expected
+                        )),
+                    Err(_) => unreachable!(),
+                }
+            }
+        }
+
+        deserializer.deserialize_string(ReloadIntervalVisitor)
+    }
+}
+
+
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq, Hash)]
+pub struct SniCertificate {
+    /// The server names this certificate is served for.  A name of the
+    /// form `*.example.com` matches exactly 1 label in place of the `*`.
+    #[serde(rename = "server-names")]
+    pub server_names: Vec<String>,
+    #[serde(default)]
     pub certificate: PathBuf,
-    #[serde(rename = "private-key")]
+    #[serde(default, rename = "private-key")]
     pub private_key: PathBuf,
+    #[serde(default, rename = "private-key-passphrase")]
+    pub private_key_passphrase: Option<Secret>,
+    #[serde(default)]
+    pub pkcs12: Option<PathBuf>,
+}
+
+impl Parse for Vec<SniCertificate> {
+    /// Parse a `TOML` array of inline tables
+    /// e.g. `[{ server-names = ["example.com"], pkcs12 = "example.p12" }]`.
+    fn parse(string: &str) -> Result<Self, AtError> {
+        crate::core::parse_toml_value(string)
+    }
+}
+
+
+/// A secret value that is read from elsewhere rather than being written
+/// into the settings file itself.  In `TOML`, it is written as either
+/// `{ env = "VAR_NAME" }` or `{ file = "path/to/file" }`.
+#[derive(Debug, Clone, Deserialize, PartialEq, Eq, Hash)]
+pub enum Secret {
+    /// The value of an environment variable.
+    #[serde(rename = "env")]
+    Env(String),
+    /// The contents of a file, minus 1 trailing newline if present.
+    #[serde(rename = "file")]
+    File(PathBuf),
+}
+
+impl Secret {
+    /// Read the secret value.
+    pub fn read(&self) -> Result<String, AtError> {
+        match self {
+            Self::Env(var_name) => Ok(std::env::var(var_name)?),
+            Self::File(path) => {
+                let mut contents = std::fs::read_to_string(path)
+                    .map_err(|error| AtError::UnreadableFile {
+                        path: path.clone(),
+                        error: error.into(),
+                    })?;
+                if contents.ends_with('\n') { contents.pop(); }
+                if contents.ends_with('\r') { contents.pop(); }
+                Ok(contents)
+            },
+        }
+    }
+}
+
+impl serde::Serialize for Secret {
+    /// Serialize into a table, since `TOML` has no notation for enum
+    /// variants that hold a value.
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where S: serde::Serializer {
+        use serde::ser::SerializeMap;
+        let mut map = serializer.serialize_map(Some(1))?;
+        match self {
+            Self::Env(var_name) => map.serialize_entry("env", var_name)?,
+            Self::File(path) => map.serialize_entry("file", path)?,
+        }
+        map.end()
+    }
+}
+
+impl Parse for Secret {
+    /// Parse a `TOML` inline table e.g. `{ env = "KEY_PASSPHRASE" }`.
+    fn parse(string: &str) -> std::result::Result<Self, AtError> {
+        crate::core::parse_toml_value(string)
+    }
+}
+
+impl Parse for Option<Secret> {
+    /// Parse a secret, where an empty `string` means no secret at all.
+    fn parse(string: &str) -> std::result::Result<Self, AtError> {
+        match string {
+            "" => Ok(None),
+            string => Secret::parse(string).map(Some),
+        }
+    }
+}
+
+/// The TLS implementation used to serve `https` connections.  Each backend
+/// is only available when this crate is compiled with the cargo feature
+/// of the same name.
+#[derive(Debug, Clone, Copy, Default, Deserialize, Serialize, PartialEq, Eq, Hash)]
+pub enum TlsBackend {
+    #[default]
+    #[serde(rename = "rustls")]
+    Rustls,
+    #[serde(rename = "openssl")]
+    Openssl,
+}
+
+impl TlsBackend {
+    /// Returns `true` if support for `self` was compiled into this crate.
+    pub fn is_available(self) -> bool {
+        match self {
+            Self::Rustls  => cfg!(feature = "rustls"),
+            Self::Openssl => cfg!(feature = "openssl"),
+        }
+    }
+}
+
+impl Parse for TlsBackend {
+    fn parse(string: &str) -> std::result::Result<Self, AtError> {
+        match string {
+            "rustls"  => Ok(Self::Rustls),
+            "openssl" => Ok(Self::Openssl),
+            _ => Err(InvalidValue! {
+                expected: "\"rustls\" | \"openssl\".",
+                got: string,
+            })
+        }
+    }
+}
+
+
+/// Client certificate authentication (a.k.a. mutual TLS) settings.
+#[derive(Debug, Clone, Default, Deserialize, Serialize, PartialEq, Eq, Hash)]
+pub struct ClientAuth {
+    #[serde(default)]
+    pub mode: ClientAuthMode,
+    /// PEM files containing the CA certificates that client
+    /// certificates are verified against.
+    #[serde(default, rename = "ca-certificates")]
+    pub ca_certificates: Vec<PathBuf>,
+}
+
+#[derive(Debug, Clone, Copy, Default, Deserialize, Serialize, PartialEq, Eq, Hash)]
+pub enum ClientAuthMode {
+    /// Client certificates are neither requested nor verified.
+    #[default]
+    #[serde(rename = "none")]
+    None,
+    /// Client certificates are requested and verified if one is
+    /// presented, but anonymous clients are accepted as well.
+    #[serde(rename = "optional")]
+    Optional,
+    /// Clients must present a certificate that can be verified.
+    #[serde(rename = "required")]
+    Required,
+}
+
+impl Parse for ClientAuthMode {
+    fn parse(string: &str) -> std::result::Result<Self, AtError> {
+        match string {
+            "none"     => Ok(Self::None),
+            "optional" => Ok(Self::Optional),
+            "required" => Ok(Self::Required),
+            _ => Err(InvalidValue! {
+                expected: "\"none\" | \"optional\" | \"required\".",
+                got: string,
+            })
+        }
+    }
 }
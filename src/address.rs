@@ -0,0 +1,497 @@
+//! The addresses to listen on, and the grammar they are parsed with
+
+use crate::actix::Resolve;
+use crate::core::Parse;
+use crate::error::{AtError, AtResult};
+use serde::de;
+use std::fmt;
+use std::io;
+use std::net::{IpAddr, Ipv6Addr, SocketAddr, ToSocketAddrs};
+use std::path::PathBuf;
+use std::vec;
+
+
+impl_serialize_with_display![Address, Endpoint];
+
+
+/// A host and port to listen on.  In `TOML`, this is written either as an
+/// array e.g. `["::1", 80]`, or as a string e.g. `"[::1]:80"` or
+/// `"localhost:9000"`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Address {
+    pub host: Host,
+    pub port: u16,
+}
+
+impl fmt::Display for Address {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.host {
+            Host::Ip(IpAddr::V6(ip)) => write!(f, "[{}]:{}", ip, self.port),
+            host => write!(f, "{}:{}", host, self.port),
+        }
+    }
+}
+
+impl Address {
+    /// Resolve the host name (if any), and select the socket addresses to
+    /// bind according to `policy`.  Fails if no address is selected.
+    pub fn resolve(&self, policy: Resolve) -> io::Result<Vec<SocketAddr>> {
+        let resolved = self.to_socket_addrs()?;
+        let addrs: Vec<SocketAddr> = match policy {
+            Resolve::All   => resolved.collect(),
+            Resolve::First => resolved.take(1).collect(),
+            Resolve::Ipv4  => resolved.filter(SocketAddr::is_ipv4).collect(),
+            Resolve::Ipv6  => resolved.filter(SocketAddr::is_ipv6).collect(),
+        };
+        if addrs.is_empty() {
+            let msg = match policy {
+                Resolve::Ipv4 => format!("{} has no IPv4 address", self.host),
+                Resolve::Ipv6 => format!("{} has no IPv6 address", self.host),
+                Resolve::All | Resolve::First => format!("{} has no address", self.host),
+            };
+            return Err(io::Error::new(io::ErrorKind::AddrNotAvailable, msg));
+        }
+        Ok(addrs)
+    }
+}
+
+impl ToSocketAddrs for Address {
+    type Iter = vec::IntoIter<SocketAddr>;
+
+    /// Resolve the host name (if any) to the socket addresses to bind.
+    fn to_socket_addrs(&self) -> io::Result<Self::Iter> {
+        match &self.host {
+            Host::Ip(ip) => Ok(vec![SocketAddr::new(*ip, self.port)].into_iter()),
+            Host::Name(name) => (name.as_str(), self.port).to_socket_addrs(),
+        }
+    }
+}
+
+impl Parse for Address {
+    /// Parse either `["host", port]`, `"host:port"` or `host:port`, where
+    /// an IPv6 host is written in square brackets e.g. `[::1]:80`.
+    fn parse(string: &str) -> Result<Self, AtError> {
+        let mut parser = Parser::new(string);
+        parser.skip_whitespace();
+        let address = match parser.peek() {
+            Some('"') | Some('\'') => parser.address()?,
+            Some('[') if parser.starts_array() => parser.address()?,
+            _ => {
+                let (start, end) = parser.bare();
+                parser.host_port(start, end, None)?
+            },
+        };
+        parser.skip_whitespace();
+        parser.end()?;
+        Ok(address)
+    }
+}
+
+impl Parse for Vec<Address> {
+    /// Parse an array of addresses in any of the forms accepted by
+    /// `Address::parse`, except that `host:port` must be quoted
+    /// e.g. `[["0.0.0.0", 9000], "[::1]:9000"]`.
+    fn parse(string: &str) -> Result<Self, AtError> {
+        Parser::new(string).array(Parser::address)
+    }
+}
+
+impl<'de> serde::Deserialize<'de> for Address {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where D: serde::Deserializer<'de> {
+        struct AddressVisitor;
+
+        impl<'de> de::Visitor<'de> for AddressVisitor {
+            type Value = Address;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("either an array [\"host\", port] \
+                                     or a string \"host:port\"")
+            }
+
+            fn visit_str<E>(self, value: &str) -> Result<Self::Value, E>
+            where E: de::Error {
+                match Address::parse(value) {
+                    Ok(address) => Ok(address),
+                    Err(AtError::ParseAddressError { expected, column, .. }) =>
+                        Err(de::Error::invalid_value(
+                            de::Unexpected::Str(value),
+                            &format!("{} at column {}", expected, column).as_str(),
+                        )),
+                    Err(_) => unreachable!(),
+                }
+            }
+
+            fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+            where A: de::SeqAccess<'de> {
+                let host: String = seq.next_element()?
+                    .ok_or_else(|| de::Error::invalid_length(0, &self))?;
+                let host = Host::parse(&host).map_err(|_| de::Error::invalid_value(
+                    de::Unexpected::Str(&host), &"an IP address or a host name",
+                ))?;
+                let port: u16 = seq.next_element()?
+                    .ok_or_else(|| de::Error::invalid_length(1, &self))?;
+                if seq.next_element::<de::IgnoredAny>()?.is_some() {
+                    return Err(de::Error::invalid_length(3, &self));
+                }
+                Ok(Address { host, port })
+            }
+        }
+
+        deserializer.deserialize_any(AddressVisitor)
+    }
+}
+
+
+/// An entry of `ActixSettings::hosts`: Either an `Address`, or a URL whose
+/// scheme determines how it's served e.g. `"http://0.0.0.0:8080"`,
+/// `"https://0.0.0.0:8443"` or `"unix:///run/app.sock"`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Endpoint {
+    /// An address without a scheme, which serves `https` iff
+    /// `Ssl::enabled` is `true`.
+    Address(Address),
+    /// `http://host:port`, which always serves plain `http`.  The port
+    /// defaults to 80.
+    Http(Address),
+    /// `https://host:port`, which always serves `https` as configured by
+    /// the `Ssl` settings.  The port defaults to 443.
+    Https(Address),
+    /// `unix://path`, a Unix domain socket serving plain `http`
+    /// e.g. `unix:///run/app.sock` for the absolute path `/run/app.sock`.
+    Unix(PathBuf),
+}
+
+impl fmt::Display for Endpoint {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Address(address) => write!(f, "{}", address),
+            Self::Http(address) => write!(f, "http://{}", address),
+            Self::Https(address) => write!(f, "https://{}", address),
+            Self::Unix(path) => write!(f, "unix://{}", path.display()),
+        }
+    }
+}
+
+impl From<Address> for Endpoint {
+    fn from(address: Address) -> Self { Self::Address(address) }
+}
+
+impl Parse for Endpoint {
+    /// Parse any of the forms accepted by `Address::parse`, or a URL
+    /// e.g. `https://[::1]:8443` or `unix:///run/app.sock`.
+    fn parse(string: &str) -> Result<Self, AtError> {
+        let mut parser = Parser::new(string);
+        parser.skip_whitespace();
+        let endpoint = match parser.peek() {
+            Some('"') | Some('\'') => parser.endpoint()?,
+            Some('[') if parser.starts_array() => parser.endpoint()?,
+            _ => {
+                let (start, end) = parser.bare();
+                parser.url(start, end)?
+            },
+        };
+        parser.skip_whitespace();
+        parser.end()?;
+        Ok(endpoint)
+    }
+}
+
+impl Parse for Vec<Endpoint> {
+    /// Parse an array of endpoints in any of the forms accepted by
+    /// `Endpoint::parse`, except that strings must be quoted
+    /// e.g. `[["0.0.0.0", 9000], "https://0.0.0.0:9443", "unix:///run/app.sock"]`.
+    fn parse(string: &str) -> Result<Self, AtError> {
+        Parser::new(string).array(Parser::endpoint)
+    }
+}
+
+impl<'de> serde::Deserialize<'de> for Endpoint {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where D: serde::Deserializer<'de> {
+        struct EndpointVisitor;
+
+        impl<'de> de::Visitor<'de> for EndpointVisitor {
+            type Value = Endpoint;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("either an array [\"host\", port] or a string \
+                                     \"host:port\", optionally prefixed with \
+                                     \"http://\", \"https://\" or \"unix://\"")
+            }
+
+            fn visit_str<E>(self, value: &str) -> Result<Self::Value, E>
+            where E: de::Error {
+                match Endpoint::parse(value) {
+                    Ok(endpoint) => Ok(endpoint),
+                    Err(AtError::ParseAddressError { expected, column, .. }) =>
+                        Err(de::Error::invalid_value(
+                            de::Unexpected::Str(value),
+                            &format!("{} at column {}", expected, column).as_str(),
+                        )),
+                    Err(_) => unreachable!(),
+                }
+            }
+
+            fn visit_seq<A>(self, seq: A) -> Result<Self::Value, A::Error>
+            where A: de::SeqAccess<'de> {
+                use serde::Deserialize;
+                Address::deserialize(de::value::SeqAccessDeserializer::new(seq))
+                    .map(Endpoint::Address)
+            }
+        }
+
+        deserializer.deserialize_any(EndpointVisitor)
+    }
+}
+
+
+/// The host part of an `Address`: Either an IP address, or a host name that
+/// is resolved when binding.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Host {
+    Ip(IpAddr),
+    Name(String),
+}
+
+impl fmt::Display for Host {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Ip(ip) => write!(f, "{}", ip),
+            Self::Name(name) => write!(f, "{}", name),
+        }
+    }
+}
+
+impl From<IpAddr> for Host {
+    fn from(ip: IpAddr) -> Self { Self::Ip(ip) }
+}
+
+impl Parse for Host {
+    /// Parse an IP address (without square brackets), or a host name made
+    /// up of dot-separated labels of letters, digits, `-` and `_`.
+    fn parse(string: &str) -> Result<Self, AtError> {
+        if let Ok(ip) = string.parse() { return Ok(Self::Ip(ip)); }
+        let is_label = |label: &str| {
+            !label.is_empty() && label.len() <= 63 && label.chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+        };
+        if string.len() <= 253 && string.split('.').all(is_label) {
+            Ok(Self::Name(string.to_string()))
+        } else {
+            Err(Parser::new(string).error_at(0, "an IP address or a host name"))
+        }
+    }
+}
+
+
+/// A recursive descent parser for addresses, which tracks its position in
+/// `input` so that errors can report the column at which they occurred.
+struct Parser<'a> {
+    input: &'a str,
+    /// The byte offset of the next character in `input`.
+    pos: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn new(input: &'a str) -> Self {
+        Self { input, pos: 0 }
+    }
+
+    /// An error for the character at byte offset `pos`.  Columns count
+    /// characters, starting at 1.
+    fn error_at(&self, pos: usize, expected: &'static str) -> AtError {
+        AtError::ParseAddressError {
+            input: self.input.to_string(),
+            column: self.input[..pos].chars().count() + 1,
+            expected,
+        }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.input[self.pos..].chars().next()
+    }
+
+    /// Consume `c` if it's the next character.
+    fn eat(&mut self, c: char) -> bool {
+        let found = self.peek() == Some(c);
+        if found { self.pos += c.len_utf8(); }
+        found
+    }
+
+    fn expect(&mut self, c: char, expected: &'static str) -> AtResult<()> {
+        if self.eat(c) { Ok(()) } else { Err(self.error_at(self.pos, expected)) }
+    }
+
+    fn end(&self) -> AtResult<()> {
+        match self.peek() {
+            None => Ok(()),
+            Some(_) => Err(self.error_at(self.pos, "the end of the input")),
+        }
+    }
+
+    /// Skip whitespace, including newlines and `TOML` comments.
+    fn skip_whitespace(&mut self) {
+        while let Some(c) = self.peek() {
+            if c == '#' {
+                let rest = &self.input[self.pos..];
+                self.pos += rest.find('\n').unwrap_or(rest.len());
+            } else if c.is_whitespace() {
+                self.pos += c.len_utf8();
+            } else {
+                break;
+            }
+        }
+    }
+
+    /// Parse the whole input as an array of `item`s, allowing a trailing
+    /// comma.
+    fn array<T>(&mut self, item: fn(&mut Self) -> AtResult<T>) -> AtResult<Vec<T>> {
+        self.skip_whitespace();
+        self.expect('[', "`[` to start an array of addresses")?;
+        let mut items = vec![];
+        loop {
+            self.skip_whitespace();
+            if self.eat(']') { break; }
+            items.push(item(self)?);
+            self.skip_whitespace();
+            if self.eat(']') { break; }
+            self.expect(',', "`,` or `]` after an address")?;
+        }
+        self.skip_whitespace();
+        self.end()?;
+        Ok(items)
+    }
+
+    /// Whether the input continues with `[` followed by a quote, as opposed
+    /// to e.g. `[::1]:80`.
+    fn starts_array(&self) -> bool {
+        self.input[self.pos..].strip_prefix('[')
+            .map(|rest| rest.trim_start().starts_with(&['"', '\''][..]))
+            .unwrap_or(false)
+    }
+
+    /// Return the byte range of the unquoted rest of the input, up to
+    /// trailing whitespace.
+    fn bare(&mut self) -> (usize, usize) {
+        let start = self.pos;
+        let end = self.input.trim_end().len().max(start);
+        self.pos = end;
+        (start, end)
+    }
+
+    /// Parse either `["host", port]` or a quoted `Endpoint`.
+    fn endpoint(&mut self) -> AtResult<Endpoint> {
+        if self.starts_array() {
+            self.address().map(Endpoint::Address)
+        } else {
+            let (start, end) = self.string()?;
+            self.url(start, end)
+        }
+    }
+
+    /// Parse `host:port`, optionally prefixed with a URL scheme, in the byte
+    /// range `start..end` of the input.
+    fn url(&self, start: usize, end: usize) -> AtResult<Endpoint> {
+        let text = &self.input[start..end];
+        let (scheme, rest) = match text.find("://") {
+            Some(i) => (&text[..i], start + i + 3),
+            None => return self.host_port(start, end, None).map(Endpoint::Address),
+        };
+        // Allow a trailing slash, as in `http://localhost:8080/`:
+        let path_end = if text.ends_with('/') && rest < end { end - 1 } else { end };
+        if let (Some(slash), "http" | "https") = (self.input[rest..path_end].find('/'), scheme) {
+            return Err(self.error_at(rest + slash, "no path after the host and port"));
+        }
+        match scheme {
+            "http" => self.host_port(rest, path_end, Some(80)).map(Endpoint::Http),
+            "https" => self.host_port(rest, path_end, Some(443)).map(Endpoint::Https),
+            "unix" if rest < end => Ok(Endpoint::Unix(PathBuf::from(&self.input[rest..end]))),
+            "unix" => Err(self.error_at(rest, "the path of the socket")),
+            _ => Err(self.error_at(start, "either `http://`, `https://` or `unix://`")),
+        }
+    }
+
+    /// Parse either `["host", port]` or `"host:port"`.
+    fn address(&mut self) -> AtResult<Address> {
+        if self.eat('[') {
+            self.skip_whitespace();
+            let (start, end) = self.string()?;
+            let host = Host::parse(&self.input[start..end])
+                .map_err(|_| self.error_at(start, "an IP address or a host name"))?;
+            self.skip_whitespace();
+            self.expect(',', "`,` after the host")?;
+            self.skip_whitespace();
+            let start = self.pos;
+            while self.peek().is_some_and(|c| c.is_ascii_digit()) { self.pos += 1; }
+            let port = self.port(start, self.pos)?;
+            self.skip_whitespace();
+            self.expect(']', "`]` after the port")?;
+            Ok(Address { host, port })
+        } else {
+            let (start, end) = self.string()?;
+            self.host_port(start, end, None)
+        }
+    }
+
+    /// Parse a quoted string, and return the byte range of its contents.
+    /// Since hosts never need escaping, escape sequences aren't supported.
+    fn string(&mut self) -> AtResult<(usize, usize)> {
+        let quote = match self.peek() {
+            Some(quote @ '"') | Some(quote @ '\'') => quote,
+            _ => return Err(self.error_at(self.pos, "a quoted string")),
+        };
+        self.pos += 1;
+        let start = self.pos;
+        loop {
+            match self.peek() {
+                None => return Err(self.error_at(self.pos, "a closing quote")),
+                Some('\\') if quote == '"' =>
+                    return Err(self.error_at(self.pos, "a host without escape sequences")),
+                Some(c) if c == quote => break,
+                Some(c) => self.pos += c.len_utf8(),
+            }
+        }
+        let end = self.pos;
+        self.pos += 1;
+        Ok((start, end))
+    }
+
+    /// Parse `host:port` in the byte range `start..end` of the input.  The
+    /// port can only be left out if there's a `default_port`.
+    fn host_port(&self, start: usize, end: usize, default_port: Option<u16>)
+    -> AtResult<Address> {
+        let text = &self.input[start..end];
+        let (host, colon) = if let Some(ip) = text.strip_prefix('[') {
+            let close = ip.find(']')
+                .ok_or_else(|| self.error_at(end, "`]` after the IPv6 address"))?;
+            let ip: Ipv6Addr = ip[..close].parse()
+                .map_err(|_| self.error_at(start + 1, "an IPv6 address"))?;
+            (Host::Ip(ip.into()), close + 2)
+        } else {
+            let colon = text.rfind(':').unwrap_or(text.len());
+            if text[..colon].contains(':') {
+                return Err(self.error_at(start, "an IPv6 address in square brackets \
+                                                 e.g. \"[::1]:80\""));
+            }
+            let host = Host::parse(&text[..colon])
+                .map_err(|_| self.error_at(start, "an IP address or a host name"))?;
+            (host, colon)
+        };
+        let port = match (&text[colon..], default_port) {
+            ("", Some(port)) => port,
+            (rest, _) if rest.starts_with(':') => self.port(start + colon + 1, end)?,
+            _ => return Err(self.error_at(start + colon, "`:` followed by a port number")),
+        };
+        Ok(Address { host, port })
+    }
+
+    /// Parse the port number in the byte range `start..end` of the input.
+    fn port(&self, start: usize, end: usize) -> AtResult<u16> {
+        let digits = &self.input[start..end];
+        if !digits.is_empty() && digits.bytes().all(|b| b.is_ascii_digit()) {
+            if let Ok(port) = digits.parse() { return Ok(port); }
+        }
+        Err(self.error_at(start, "a port number from 0 to 65535"))
+    }
+}
@@ -0,0 +1,78 @@
+//! Reporting the addresses that `TryApplySettings` actually bound
+
+use crate::actix::Listener;
+use crate::error::AtResult;
+use std::fmt;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+
+
+/// The addresses bound for each listener, in the order of
+/// `ActixSettings::all_listeners()`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BoundListeners(pub Vec<BoundListener>);
+
+impl BoundListeners {
+    /// The TCP addresses bound by all listeners, including the ports the
+    /// OS picked for listeners configured with port 0.
+    pub fn tcp_addrs(&self) -> Vec<SocketAddr> {
+        self.0.iter()
+            .flat_map(|bound| &bound.addrs)
+            .filter_map(|addr| match addr {
+                BoundAddress::Tcp(addr) => Some(*addr),
+                BoundAddress::Unix(_) => None,
+            })
+            .collect()
+    }
+
+    /// Every bound address as a URL e.g. `https://127.0.0.1:34567` or
+    /// `unix:///run/app.sock`, one per line.
+    pub fn urls(&self) -> String {
+        self.0.iter()
+            .flat_map(|bound| bound.addrs.iter().map(move |addr| UrlOf(bound, addr)))
+            .map(|url| format!("{}\n", url))
+            .collect()
+    }
+
+    /// Write `self.urls()` to the file at `path`.  The file is replaced
+    /// atomically, so that readers never see a partially written file.
+    pub fn write_port_file(&self, path: &Path) -> AtResult<()> {
+        let file_name = path.file_name().map(|name| name.to_string_lossy())
+            .unwrap_or_default();
+        let tmp = path.with_file_name(format!(".{}.tmp", file_name));
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(&tmp, self.urls())?;
+        std::fs::rename(&tmp, path)?;
+        Ok(())
+    }
+}
+
+/// The addresses bound for a single listener.  A listener can bind several
+/// addresses, e.g. when its host name resolves to both an IPv4 and an IPv6
+/// address, or when it's socket-activated.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BoundListener {
+    pub listener: Listener,
+    pub addrs: Vec<BoundAddress>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BoundAddress {
+    Tcp(SocketAddr),
+    Unix(PathBuf),
+}
+
+/// Formats a bound address as a URL, whose scheme depends on the listener.
+struct UrlOf<'a>(&'a BoundListener, &'a BoundAddress);
+
+impl fmt::Display for UrlOf<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.1 {
+            BoundAddress::Tcp(addr) if self.0.listener.tls => write!(f, "https://{}", addr),
+            BoundAddress::Tcp(addr) => write!(f, "http://{}", addr),
+            BoundAddress::Unix(path) => write!(f, "unix://{}", path.display()),
+        }
+    }
+}
@@ -0,0 +1,100 @@
+//! Overriding settings with `--config` and `--set` command-line arguments
+
+use crate::error::{AtError, AtResult};
+use std::fmt::Write;
+use std::path::{Path, PathBuf};
+
+
+/// The settings-related arguments of a command line such as
+/// `server --config /etc/app/Server.toml --set actix.num-workers=8`:
+///
+/// * `--config PATH` or `--config=PATH`: The settings file to read.  A later
+///   `--config` replaces an earlier one.
+/// * `--set KEY=VALUE` or `--set=KEY=VALUE`, repeatedly: The settings to set
+///   with `BasicSettings::set()`, in order, after all other layers.
+///
+/// All other arguments are kept in `args` for the application, as are all
+/// arguments after a `--`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CommandLine {
+    /// The name the program was invoked by, i.e. the first argument.
+    pub program: String,
+    pub config: Option<PathBuf>,
+    /// The `(key, value)` pair of each `--set`, in order.
+    pub set: Vec<(String, String)>,
+    /// The arguments that aren't settings-related, in order.
+    pub args: Vec<String>,
+}
+
+impl CommandLine {
+    /// Extract the settings-related arguments from `args`, the first of
+    /// which is the name of the program, as with `std::env::args()`.
+    pub fn parse<I>(args: I) -> AtResult<Self>
+    where I: IntoIterator<Item = String> {
+        let mut args = args.into_iter();
+        let mut command_line = Self {
+            program: args.next().unwrap_or_default(),
+            ..Self::default()
+        };
+        let missing = |option: &str| AtError::InvalidArgument(
+            format!("{} requires a value", option)
+        );
+        while let Some(arg) = args.next() {
+            let (option, value) = match arg.split_once('=') {
+                Some((option, value)) if option.starts_with("--") =>
+                    (option.to_string(), Some(value.to_string())),
+                _ => (arg, None),
+            };
+            match option.as_str() {
+                "--config" => {
+                    let path = value.or_else(|| args.next()).ok_or_else(|| missing(&option))?;
+                    command_line.config = Some(PathBuf::from(path));
+                },
+                "--set" => {
+                    let pair = value.or_else(|| args.next()).ok_or_else(|| missing(&option))?;
+                    let (key, value) = pair.split_once('=').ok_or_else(|| {
+                        AtError::InvalidArgument(format!("--set expects KEY=VALUE, got {:?}", pair))
+                    })?;
+                    command_line.set.push((key.to_string(), value.to_string()));
+                },
+                "--" => {
+                    command_line.args.extend(args.by_ref());
+                },
+                _ => command_line.args.push(match value {
+                    Some(value) => format!("{}={}", option, value),
+                    None => option,
+                }),
+            }
+        }
+        Ok(command_line)
+    }
+
+    /// Extract the settings-related arguments from the arguments that the
+    /// process was started with.
+    pub fn from_env() -> AtResult<Self> {
+        Self::parse(std::env::args())
+    }
+
+    /// The `--config` path if given, or else `default`.
+    pub fn config_path<P: AsRef<Path>>(&self, default: P) -> PathBuf {
+        self.config.clone().unwrap_or_else(|| default.as_ref().to_path_buf())
+    }
+
+    /// A message for `error` that explains the settings-related arguments,
+    /// and lists the valid `keys` for `--set` if `error` is about an unknown
+    /// key.
+    pub fn usage(&self, error: &AtError, keys: &[String]) -> String {
+        let mut usage = format!(
+            "error: {}\n\nUsage: {} [--config PATH] [--set KEY=VALUE]...\n",
+            std::io::Error::from(error.clone()),
+            self.program,
+        );
+        if let AtError::UnknownSetting(_) = error {
+            usage.push_str("\nValid keys:\n");
+            for key in keys {
+                let _ = writeln!(usage, "    {}", key);
+            }
+        }
+        usage
+    }
+}
@@ -1,4 +1,5 @@
 use crate::error::AtError;
+use serde_derive::Deserialize;
 use std::path::PathBuf;
 use std::str::FromStr;
 
@@ -6,6 +7,22 @@ pub trait Parse: Sized {
     fn parse(string: &str) -> Result<Self, AtError>;
 }
 
+/// Implement `serde::Serialize` for types that are written as strings in
+/// `TOML`, by way of their `fmt::Display` impls, which must produce strings
+/// that their `Parse` impls accept.
+macro_rules! impl_serialize_with_display {
+    ($($type:ty),+ $(,)?) => {
+        $(
+            impl serde::Serialize for $type {
+                fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+                where S: serde::Serializer {
+                    serializer.collect_str(self)
+                }
+            }
+        )+
+    }
+}
+
 impl Parse for bool {
     fn parse(string: &str) -> Result<Self, AtError> {
         Self::from_str(string).map_err(AtError::from)
@@ -29,8 +46,53 @@ impl Parse for String {
     fn parse(string: &str) -> Result<Self, AtError> { Ok(string.to_string()) }
 }
 
+impl Parse for Option<String> {
+    /// Parse a string, where an empty `string` means no string at all.
+    fn parse(string: &str) -> Result<Self, AtError> {
+        match string {
+            "" => Ok(None),
+            string => String::parse(string).map(Some),
+        }
+    }
+}
+
 impl Parse for PathBuf {
     fn parse(string: &str) -> Result<Self, AtError> {
         Ok(PathBuf::from(string))
     }
 }
+
+impl Parse for Option<PathBuf> {
+    /// Parse a path, where an empty `string` means no path at all.
+    fn parse(string: &str) -> Result<Self, AtError> {
+        match string {
+            "" => Ok(None),
+            string => PathBuf::parse(string).map(Some),
+        }
+    }
+}
+
+/// Parse `string` as the `TOML` representation of a `T` value,
+/// e.g. `["a.pem", "b.pem"]` for a `Vec<PathBuf>`.
+pub(crate) fn parse_toml_value<T>(string: &str) -> Result<T, AtError>
+where T: serde::de::DeserializeOwned {
+    #[derive(Deserialize)]
+    #[serde(bound = "T: serde::de::DeserializeOwned")]
+    struct Wrapper<T> { value: T }
+    let wrapper: Wrapper<T> = toml::from_str(&format!("value = {}", string))?;
+    Ok(wrapper.value)
+}
+
+impl Parse for Vec<PathBuf> {
+    /// Parse a `TOML` array of strings e.g. `["a.pem", "b.pem"]`.
+    fn parse(string: &str) -> Result<Self, AtError> {
+        parse_toml_value(string)
+    }
+}
+
+impl Parse for Vec<String> {
+    /// Parse a `TOML` array of strings e.g. `["h2", "http/1.1"]`.
+    fn parse(string: &str) -> Result<Self, AtError> {
+        parse_toml_value(string)
+    }
+}
@@ -1,17 +1,47 @@
 use ioe;
 use std::env::VarError;
 use std::io::{self, Error as IoError};
+use crate::actix::{ListenAddress, TlsBackend};
 use std::path::PathBuf;
 use std::num::ParseIntError;
 use std::str::ParseBoolError;
 use toml::de::Error as TomlError;
+use toml::ser::Error as TomlSerError;
 
 pub type AtResult<T> = std::result::Result<T, AtError>;
 
 #[derive(Clone, Debug)]
 pub enum AtError {
+    BindError {
+        address: ListenAddress,
+        error: ioe::IoError,
+    },
+    /// Changing the root directory to `path` failed.
+    ChrootError {
+        path: PathBuf,
+        error: ioe::IoError,
+    },
+    /// Switching to the `what` ("user", "group" or "supplementary groups")
+    /// with the given `id` failed.
+    DropPrivilegesError {
+        what: &'static str,
+        id: u32,
+        error: ioe::IoError,
+    },
+    DuplicateServerName(String),
     EnvVarError(VarError),
     FileExists(PathBuf),
+    /// A command-line argument is malformed, e.g. `--set` without a value.
+    InvalidArgument(String),
+    InvalidCertificate(PathBuf),
+    /// The environment variable `name` is set to `value`, which isn't
+    /// `expected`.
+    InvalidEnvVar {
+        name: String,
+        value: String,
+        expected: &'static str,
+    },
+    InvalidPrivateKey(PathBuf),
     InvalidValue {
         expected: &'static str,
         got: String,
@@ -20,10 +50,39 @@ pub enum AtError {
         column: u32,
     },
     IoError(ioe::IoError),
+    KeyMismatch {
+        certificate: PathBuf,
+        private_key: PathBuf,
+    },
     ParseBoolError(ParseBoolError),
     ParseIntError(ParseIntError),
-    ParseAddressError(String),
+    /// `column` is the position of the offending character in `input`,
+    /// counting characters from 1.
+    ParseAddressError {
+        input: String,
+        column: usize,
+        expected: &'static str,
+    },
+    SelfSignedCertificateInProduction,
+    SocketActivationError(String),
+    TlsBackendUnavailable(TlsBackend),
+    TlsError(String),
     TomlError(TomlError),
+    /// Serializing settings into a `TOML` value failed.
+    TomlSerError(TomlSerError),
+    /// Environment variables with the prefix given to
+    /// `apply_env_overrides_strict()` that don't name a setting, each with
+    /// the name of the variable it's likely a typo of, if any.
+    UnknownEnvVars(Vec<(String, Option<String>)>),
+    UnknownGroup(String),
+    /// The dotted path e.g. `"actix.num-worker"` doesn't name a setting.
+    UnknownSetting(String),
+    UnknownUser(String),
+    UnreadableFile {
+        path: PathBuf,
+        error: ioe::IoError,
+    },
+    WrongPassphrase(PathBuf),
 }
 
 macro_rules! InvalidValue {
@@ -58,6 +117,10 @@ impl From<TomlError> for AtError {
     fn from(err: TomlError) -> Self { Self::TomlError(err) }
 }
 
+impl From<TomlSerError> for AtError {
+    fn from(err: TomlSerError) -> Self { Self::TomlSerError(err) }
+}
+
 impl From<VarError> for AtError {
     fn from(err: VarError) -> Self { Self::EnvVarError(err) }
 }
@@ -66,6 +129,22 @@ impl From<VarError> for AtError {
 impl From<AtError> for IoError {
     fn from(err: AtError) -> Self {
         match err {
+            AtError::BindError { address, error } => {
+                let msg = format!("Failed to bind {}: {}", address, error);
+                IoError::new(error.kind().into(), msg)
+            },
+            AtError::ChrootError { path, error } => {
+                let msg = format!("Failed to chroot to {}: {}", path.display(), error);
+                IoError::new(error.kind().into(), msg)
+            },
+            AtError::DropPrivilegesError { what, id, error } => {
+                let msg = format!("Failed to switch to {} {}: {}", what, id, error);
+                IoError::new(error.kind().into(), msg)
+            },
+            AtError::DuplicateServerName(name) => {
+                let msg = format!("Server name used by multiple certificates: {}", name);
+                IoError::new(io::ErrorKind::InvalidInput, msg)
+            },
             AtError::EnvVarError(var_error) => {
                 let msg = format!("Env var error: {}", var_error);
                 IoError::new(io::ErrorKind::InvalidInput, msg)
@@ -74,12 +153,30 @@ impl From<AtError> for IoError {
                 let msg = format!("File exists: {}", path_buf.display());
                 IoError::new(io::ErrorKind::AlreadyExists, msg)
             },
+            AtError::InvalidArgument(msg) => IoError::new(io::ErrorKind::InvalidInput, msg),
+            AtError::InvalidCertificate(path_buf) => {
+                let msg = format!("Invalid certificate: {}", path_buf.display());
+                IoError::new(io::ErrorKind::InvalidData, msg)
+            },
+            AtError::InvalidEnvVar { name, value, expected } => {
+                let msg = format!("Env var {} is {:?}, expected {}", name, value, expected);
+                IoError::new(io::ErrorKind::InvalidInput, msg)
+            },
+            AtError::InvalidPrivateKey(path_buf) => {
+                let msg = format!("Invalid private key: {}", path_buf.display());
+                IoError::new(io::ErrorKind::InvalidData, msg)
+            },
             AtError::InvalidValue { expected, ref got, file, line, column } => {
                 let msg = format!("Expected {}, got {}  (@ {}:{}:{})",
                                   expected, got, file, line, column);
                 IoError::new(io::ErrorKind::InvalidInput, msg)
             },
             AtError::IoError(io_error) => io_error.into(),
+            AtError::KeyMismatch { certificate, private_key } => {
+                let msg = format!("Private key {} doesn't match certificate {}",
+                                  private_key.display(), certificate.display());
+                IoError::new(io::ErrorKind::InvalidData, msg)
+            },
             AtError::ParseBoolError(parse_bool_error) => {
                 let msg = format!("Failed to parse boolean: {}", parse_bool_error);
                 IoError::new(io::ErrorKind::InvalidInput, msg)
@@ -88,14 +185,66 @@ impl From<AtError> for IoError {
                 let msg = format!("Failed to parse integer: {}", parse_int_error);
                 IoError::new(io::ErrorKind::InvalidInput, msg)
             },
-            AtError::ParseAddressError(string) => {
-                let msg = format!("Failed to parse address: {}", string);
+            AtError::ParseAddressError { input, column, expected } => {
+                let msg = format!("Failed to parse address {:?}: expected {} at column {}",
+                                  input, expected, column);
                 IoError::new(io::ErrorKind::InvalidInput, msg)
             },
+            AtError::SelfSignedCertificateInProduction => {
+                let msg = "Refusing to generate a self-signed certificate in \
+                           production mode; disable `auto-generate` or switch to \
+                           development mode".to_string();
+                IoError::new(io::ErrorKind::PermissionDenied, msg)
+            },
+            AtError::SocketActivationError(string) => {
+                let msg = format!("Socket activation failed: {}", string);
+                IoError::new(io::ErrorKind::InvalidInput, msg)
+            },
+            AtError::TlsBackendUnavailable(backend) => {
+                let msg = format!("TLS backend {:?} requires this crate to be \
+                                   compiled with the matching cargo feature", backend);
+                IoError::new(io::ErrorKind::Unsupported, msg)
+            },
+            AtError::TlsError(string) => {
+                let msg = format!("TLS error: {}", string);
+                IoError::new(io::ErrorKind::InvalidData, msg)
+            },
             AtError::TomlError(toml_error) => {
                 let msg = format!("TOML error: {}", toml_error);
                 IoError::new(io::ErrorKind::InvalidInput, msg)
             },
+            AtError::TomlSerError(toml_error) => {
+                let msg = format!("TOML serialization error: {}", toml_error);
+                IoError::new(io::ErrorKind::InvalidData, msg)
+            },
+            AtError::UnknownEnvVars(vars) => {
+                let vars: Vec<String> = vars.iter().map(|(name, suggestion)| match suggestion {
+                    Some(suggestion) => format!("{} (did you mean {}?)", name, suggestion),
+                    None => name.clone(),
+                }).collect();
+                let msg = format!("Unknown env vars: {}", vars.join(", "));
+                IoError::new(io::ErrorKind::InvalidInput, msg)
+            },
+            AtError::UnknownGroup(name) => {
+                let msg = format!("Unknown group: {}", name);
+                IoError::new(io::ErrorKind::NotFound, msg)
+            },
+            AtError::UnknownSetting(path) => {
+                let msg = format!("Unknown setting: {}", path);
+                IoError::new(io::ErrorKind::InvalidInput, msg)
+            },
+            AtError::UnknownUser(name) => {
+                let msg = format!("Unknown user: {}", name);
+                IoError::new(io::ErrorKind::NotFound, msg)
+            },
+            AtError::UnreadableFile { path, error } => {
+                let msg = format!("Failed to read {}: {}", path.display(), error);
+                IoError::new(error.kind().into(), msg)
+            },
+            AtError::WrongPassphrase(path_buf) => {
+                let msg = format!("Wrong or missing passphrase for {}", path_buf.display());
+                IoError::new(io::ErrorKind::PermissionDenied, msg)
+            },
         }
     }
 }
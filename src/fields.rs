@@ -0,0 +1,64 @@
+//! Addressing the fields of `ActixSettings` by their dotted `TOML` keys
+
+use crate::actix::ActixSettings;
+use crate::core::Parse;
+use crate::error::AtResult;
+
+
+macro_rules! actix_fields {
+    ($($key:literal => $($field:ident).+),+ $(,)?) => {
+        /// The dotted `TOML` key of every field of `ActixSettings` that can
+        /// be set from a string, relative to the `[actix]` table.
+        pub(crate) const ACTIX_KEYS: &[&str] = &[$($key),+];
+
+        /// Set the field of `settings` at `key` e.g. `"ssl.enabled"` by
+        /// parsing `value` with its `Parse` impl.  Returns `None` if there's
+        /// no such field.
+        pub(crate) fn set_actix_field(
+            settings: &mut ActixSettings,
+            key: &str,
+            value: &str,
+        ) -> Option<AtResult<()>> {
+            match key {
+                $($key => Some(Parse::parse(value).map(|parsed| settings.$($field).+ = parsed)),)+
+                _ => None,
+            }
+        }
+    };
+}
+
+actix_fields! {
+    "hosts"                        => hosts,
+    "port-from-env"                => port_from_env,
+    "listeners"                    => listeners,
+    "mode"                         => mode,
+    "enable-compression"           => enable_compression,
+    "enable-log"                   => enable_log,
+    "num-workers"                  => num_workers,
+    "backlog"                      => backlog,
+    "max-connections"              => max_connections,
+    "max-connection-rate"          => max_connection_rate,
+    "keep-alive"                   => keep_alive,
+    "client-timeout"               => client_timeout,
+    "client-shutdown"              => client_shutdown,
+    "shutdown-timeout"             => shutdown_timeout,
+    "port-file"                    => port_file,
+    "process.user"                 => process.user,
+    "process.group"                => process.group,
+    "process.chroot"               => process.chroot,
+    "ssl.enabled"                  => ssl.enabled,
+    "ssl.backend"                  => ssl.backend,
+    "ssl.certificate"              => ssl.certificate,
+    "ssl.private-key"              => ssl.private_key,
+    "ssl.private-key-passphrase"   => ssl.private_key_passphrase,
+    "ssl.pkcs12"                   => ssl.pkcs12,
+    "ssl.auto-generate"            => ssl.auto_generate,
+    "ssl.client-auth.mode"         => ssl.client_auth.mode,
+    "ssl.client-auth.ca-certificates" => ssl.client_auth.ca_certificates,
+    "ssl.certificates"             => ssl.certificates,
+    "ssl.reload-interval"          => ssl.reload_interval,
+    "ssl.min-version"              => ssl.min_version,
+    "ssl.max-version"              => ssl.max_version,
+    "ssl.cipher-suites"            => ssl.cipher_suites,
+    "ssl.alpn"                     => ssl.alpn,
+}
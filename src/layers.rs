@@ -0,0 +1,113 @@
+//! Layering configuration files on top of each other
+
+use crate::actix::Mode;
+use crate::core::Parse;
+use crate::error::{AtError, AtResult};
+use std::path::{Path, PathBuf};
+use toml::Value;
+
+
+/// How `BasicSettings::parse_layered_toml()` finds and merges the files that
+/// are layered on top of the base file, e.g. `Server.toml`:
+///
+/// 1. `Server.{mode}.toml`, where `mode` is `"development"` or
+///    `"production"`, as set by the `mode_env_var` environment variable or
+///    else by `actix.mode` in the base file.
+/// 2. `Server.local.toml`, for settings that aren't shared e.g. secrets.
+///
+/// Files that don't exist are skipped.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Layers {
+    /// The name of an environment variable that overrides `actix.mode`,
+    /// e.g. `"APP_MODE"`.
+    pub mode_env_var: Option<String>,
+    pub tables: TableMerge,
+    pub arrays: ArrayMerge,
+}
+
+/// How a table in a layered file, such as `[actix.ssl]`, is merged with the
+/// table at the same key in the files below it.  The top-level tables, i.e.
+/// `[actix]` and `[application]`, are always merged key by key.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TableMerge {
+    /// Merge the tables key by key, recursively, so that a layer only needs
+    /// to contain the keys that it changes.
+    #[default]
+    Deep,
+    /// Replace the whole table.
+    Replace,
+}
+
+/// How an array in a layered file, including an array of tables like
+/// `[[actix.listeners]]`, is merged with the array at the same key in the
+/// files below it.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ArrayMerge {
+    /// Replace the whole array.
+    #[default]
+    Replace,
+    /// Append the elements of the layered array.
+    Append,
+}
+
+impl Layers {
+    /// The mode set by the `mode_env_var` environment variable, if any.
+    pub(crate) fn env_mode(&self) -> AtResult<Option<String>> {
+        let name = match &self.mode_env_var {
+            Some(name) => name,
+            None => return Ok(None),
+        };
+        match std::env::var(name) {
+            Err(std::env::VarError::NotPresent) => Ok(None),
+            Err(err) => Err(err.into()),
+            Ok(value) => match Mode::parse(&value) {
+                Ok(_) => Ok(Some(value)),
+                Err(_) => Err(AtError::InvalidEnvVar {
+                    name: name.clone(),
+                    value,
+                    expected: "\"development\" or \"production\"",
+                }),
+            },
+        }
+    }
+
+    /// Merge the `layer` file into the `base` file.
+    pub(crate) fn merge(&self, base: &mut Value, layer: Value) {
+        self.merge_at(base, layer, 0);
+    }
+
+    /// Merge `layer` into `base`, which are nested `depth` tables deep.
+    fn merge_at(&self, base: &mut Value, layer: Value, depth: usize) {
+        match (base, layer) {
+            (Value::Table(base), Value::Table(layer))
+                if depth <= 1 || self.tables == TableMerge::Deep => {
+                for (key, value) in layer {
+                    match base.get_mut(&key) {
+                        Some(base_value) => self.merge_at(base_value, value, depth + 1),
+                        None => { base.insert(key, value); },
+                    }
+                }
+            },
+            (Value::Array(base), Value::Array(layer)) if self.arrays == ArrayMerge::Append =>
+                base.extend(layer),
+            (base, layer) => *base = layer,
+        }
+    }
+}
+
+/// The `actix.mode` in `settings`, if any.
+pub(crate) fn mode(settings: &Value) -> Option<&str> {
+    settings.get("actix")?.get("mode")?.as_str()
+}
+
+/// The path of the layer called `name` for the base file at `path`
+/// e.g. `Server.local.toml` for `Server.toml`.
+pub(crate) fn layer_path(path: &Path, name: &str) -> PathBuf {
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    match path.extension() {
+        Some(extension) => path.with_file_name(
+            format!("{}.{}.{}", stem, name, extension.to_string_lossy())
+        ),
+        None => path.with_file_name(format!("{}.{}", stem, name)),
+    }
+}
@@ -1,8 +1,21 @@
 /// A library to process Server.toml files
 
 #[macro_use] mod error;
-mod core;
+#[macro_use] mod core;
+mod address;
 mod actix;
+mod tls;
+mod bound;
+mod command_line;
+mod fields;
+mod layers;
+mod overrides;
+mod preflight;
+mod socket;
+#[cfg(unix)] mod process;
+#[cfg(unix)] mod systemd;
+#[cfg(unix)] mod unix;
+#[cfg(test)] mod testing;
 
 use actix_http::{KeepAlive as ActixKeepAlive, Request, Response};
 use actix_service::{IntoServiceFactory, ServiceFactory};
@@ -10,8 +23,19 @@ use actix_web::{Error as WebError, HttpServer};
 use actix_web::dev::{AppConfig, MessageBody, Service};
 pub use crate::core::Parse;
 pub use crate::actix::*;
+pub use crate::bound::{BoundAddress, BoundListener, BoundListeners};
+pub use crate::command_line::CommandLine;
+pub use crate::layers::{ArrayMerge, Layers, TableMerge};
+pub use crate::preflight::{PreflightCheck, PreflightReport, PreflightStatus};
 pub use crate::error::{AtError, AtResult};
-use serde_derive::Deserialize;
+use ioe::IoError;
+#[cfg(feature = "openssl")]
+pub use crate::tls::openssl_acceptor_builder;
+#[cfg(feature = "rustls")]
+pub use crate::tls::rustls_server_config;
+use serde_derive::{Deserialize, Serialize};
+#[cfg(unix)]
+use std::collections::HashSet;
 use std::env::{self, VarError};
 use std::io::{Read, Write};
 use std::fmt::Debug;
@@ -27,7 +51,7 @@ pub struct BasicSettings<A> {
 
 pub type Settings = BasicSettings::<NoSettings>;
 
-#[derive(Debug, Clone, Deserialize, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq, Hash)]
 pub struct NoSettings {/* NOTE: **DO NOT** turn this into a unit struct */}
 
 
@@ -41,10 +65,24 @@ where A: for<'de> serde::de::Deserialize<'de> {
 [actix]
 # For more info, see: https://docs.rs/actix-web/3.1.0/actix_web/struct.HttpServer.html.
 
+# Each entry is either an array ["host", port] or a string "host:port", where
+# the host is an IP address or a host name.  IPv6 addresses are written in
+# square brackets in the string form e.g. "[::1]:9000".
+# Strings can also be URLs, whose scheme determines how they're served:
+# "http://0.0.0.0:8080" always serves http, "https://0.0.0.0:8443" always
+# serves https as configured by [actix.ssl], and "unix:///run/app.sock" serves
+# http on a Unix domain socket.  Entries without a scheme serve https iff
+# [actix.ssl] is `enabled`.
 hosts = [
     ["0.0.0.0", 9000]      # This should work for both development and deployment...
     #                      # ... but other entries are possible, as well.
 ]
+# The name of an environment variable holding the port to serve the `hosts` on,
+# as set by many PaaS platforms and container orchestrators.  When it's set,
+# its value replaces the port of every `hosts` entry other than Unix sockets,
+# or adds "0.0.0.0" with that port if there are no such entries.  The
+# `[[actix.listeners]]` keep their own addresses.
+# port-from-env = "PORT"
 mode = "development"       # Either "development" or "production".
 enable-compression = true  # Toggle compression middleware.
 enable-log = true          # Toggle logging middleware.
@@ -102,10 +140,129 @@ client-shutdown = "default"
 # where N is an integer > 0 e.g. "6 seconds".
 shutdown-timeout = "default"
 
+# The path of a file to which the actually bound addresses are written once all
+# listeners are bound, one URL per line e.g. "http://127.0.0.1:34567".  This is
+# useful to find the ports picked by the OS for hosts with port 0.
+# port-file = "/run/myapp/ports"
+
+# Listeners with options of their own, bound in addition to the `hosts` above.
+# Each one has either an `address` in the same format as the `hosts` entries,
+# or the path of a `unix` domain socket.  TCP listeners can have:
+# - `tls`: Whether to serve https as configured by [actix.ssl], regardless of
+#   its `enabled` key.  Defaults to false i.e. plain http.
+# - `certificate` and `private-key`, or `pkcs12`, and `private-key-passphrase`:
+#   A certificate to serve instead of the default one in [actix.ssl].
+# - `backlog`: Replaces the `backlog` above for this listener.
+# - `resolve`: Which addresses of a host name to bind: Either "all" (the
+#   default), "first" (the first one returned by the resolver), "ipv4" or "ipv6".
+# - Socket options, which keep the OS defaults unless set: `reuse-port`,
+#   `reuse-address`, `nodelay` and `ipv6-only` (true or false), `keepalive-idle`
+#   and `keepalive-interval` (e.g. "60 seconds"), `keepalive-count`, and
+#   `send-buffer-size` and `recv-buffer-size` in bytes.
+# Unix listeners only serve plain http, and can have:
+# - `mode`: The permissions of the socket file as an octal string e.g. "0660".
+# - `owner`: The owner of the socket file: "user", "user:group" or ":group".
+# - `stale-socket`: What to do if the socket file already exists: Either
+#   "remove" (unless another process accepts connections on it), "force"
+#   (remove it regardless) or "keep" (binding fails).  Defaults to "remove".
+#   Files other than sockets are never removed.
+# Listeners with `activation = "systemd"` use the sockets that systemd passes
+# to this process (see `LISTEN_FDS` in sd_listen_fds(3)), and can have:
+# - `fd-name`: The `FileDescriptorName=` of the sockets to use.  Without it,
+#   the listener uses the sockets that no other listener names.
+# - `tls` and its certificate options, as for TCP listeners.
+# Other limits, like `max-connections` and `keep-alive`, apply server-wide.
+# [[actix.listeners]]
+# address = ["0.0.0.0", 8080]
+#
+# [[actix.listeners]]
+# address = ["0.0.0.0", 8443]
+# tls = true
+# backlog = "2048"
+# nodelay = true
+# keepalive-idle = "60 seconds"
+#
+# [[actix.listeners]]
+# unix = "/run/app.sock"
+# mode = "0660"
+# owner = "app:www-data"
+#
+# [[actix.listeners]]
+# activation = "systemd"
+# fd-name = "https"
+# tls = true
+
 [actix.ssl] # SSL is disabled by default because the certs don't exist
-enabled = false
-certificate = "path/to/cert/cert.pem"
-private-key = "path/to/cert/key.pem"
+enabled = false  # Whether the `hosts` without a URL scheme serve https.
+# The TLS implementation to use: Either "rustls" or "openssl".
+# The chosen backend must be enabled as a cargo feature of the same name.
+backend = "rustls"
+certificate = "path/to/cert/cert.pem"  # PEM-encoded certificate chain
+private-key = "path/to/cert/key.pem"   # PEM-encoded PKCS#8 or RSA private key
+
+# The passphrase of an encrypted `private-key` or `pkcs12` bundle.  Rather than
+# being written here, it's read from either an environment variable, or a file
+# e.g. { file = "/run/secrets/tls-passphrase" }.
+# private-key-passphrase = { env = "TLS_PASSPHRASE" }
+
+# A PKCS#12 bundle holding both the certificate chain and the private key.  If
+# present, it's used instead of `certificate` and `private-key`.
+# Reading encrypted private keys and PKCS#12 bundles requires the "openssl"
+# cargo feature, even when using the "rustls" backend.
+# pkcs12 = "path/to/cert/bundle.p12"
+
+# Whether to generate a self-signed certificate for the `hosts` above when
+# neither the `certificate` nor the `private-key` file exists.  Only allowed in
+# development mode: in production mode, enabling this is an error.
+auto-generate = false
+
+# How often the certificate and private key files are checked for changes.
+# Changed files are reloaded, and used for new connections.  If reloading fails,
+# the error is logged and the current certificates remain in use.
+# Takes a string value: Either "disabled", or a string of the format "N seconds"
+# or "N milliseconds" where N is an integer > 0 e.g. "60 seconds".
+reload-interval = "disabled"
+
+# The range of protocol versions to accept: Either "1.2" or "1.3".
+# By default all versions supported by the backend are accepted.
+# min-version = "1.2"
+# max-version = "1.3"
+
+# The cipher suites to accept, by IANA name e.g. "TLS_AES_128_GCM_SHA256".
+# A protocol version without any listed cipher suite is not accepted.
+# By default the backend's defaults are used.
+cipher-suites = []
+
+# The ALPN protocols to offer, in order of preference e.g. ["h2", "http/1.1"].
+# By default actix-web's defaults are used.  With the "rustls" backend,
+# actix-web only supports its defaults i.e. ["h2", "http/1.1"].
+alpn = []
+
+[actix.ssl.client-auth] # Client certificate authentication, a.k.a. mutual TLS
+# Either "none", "optional" (verify a certificate if the client presents one),
+# or "required" (reject clients without a verifiable certificate).
+mode = "none"
+# PEM files containing the CA certificates that client certificates are
+# verified against.  At least one is required unless `mode` is "none".
+ca-certificates = []
+
+# Additional certificates, selected by SNI (Server Name Indication) during the
+# TLS handshake.  Clients asking for any other server name, or none at all, are
+# served the `certificate` above.  Each server name may only be listed once.
+# [[actix.ssl.certificates]]
+# server-names = ["example.com", "*.example.com"]
+# certificate = "path/to/example.com/cert.pem"
+# private-key = "path/to/example.com/key.pem"
+
+# The identity that `drop_privileges()` switches to once all listeners are bound,
+# e.g. to bind ports below 1024 as root and then serve requests as an
+# unprivileged user (Unix only).
+# The `chroot` directory is applied first, and then the `group` (defaulting to
+# the primary group of `user`) and the `user`, each a name or a numeric id.
+# [actix.process]
+# user = "www-data"
+# group = "www-data"
+# chroot = "/var/empty"
 
 # The `application` table be used to express application-specific settings.
 # See the `README.md` file for more details on how to use this.
@@ -122,7 +279,65 @@ private-key = "path/to/cert/key.pem"
         let mut f = File::open(filepath)?;
         let mut contents = String::with_capacity(f.metadata()?.len() as usize);
         f.read_to_string(&mut contents)?;
-        Ok(toml::from_str::<Self>(&contents)?)
+        Self::from_template(&contents)
+    }
+
+    /// Parse an instance of `Self` from the `TOML` file at `filepath` like
+    /// [`parse_toml`], with the files described by `layers` merged on top of
+    /// it, e.g. `Server.development.toml` and `Server.local.toml` for
+    /// `Server.toml`.  If the environment variable `layers.mode_env_var` is
+    /// set, its value also replaces `actix.mode`.
+    ///
+    /// [`parse_toml`]: #method.parse_toml
+    pub fn parse_layered_toml<P>(filepath: P, layers: &Layers) -> AtResult<Self>
+    where P: AsRef<Path> {
+        let filepath = filepath.as_ref();
+        if !filepath.exists() { Self::write_toml_file(filepath)?; }
+        let read = |path: &Path| -> AtResult<toml::Value> {
+            Ok(toml::from_str(&std::fs::read_to_string(path)?)?)
+        };
+        let mut settings = read(filepath)?;
+        let env_mode = layers.env_mode()?;
+        let mode = env_mode.clone().or_else(|| layers::mode(&settings).map(String::from));
+        for name in mode.iter().map(String::as_str).chain(Some("local")) {
+            let path = layers::layer_path(filepath, name);
+            if path.exists() {
+                let layer = read(&path)?;
+                layers.merge(&mut settings, layer);
+            }
+        }
+        let actix = settings.get_mut("actix");
+        if let (Some(mode), Some(toml::Value::Table(actix))) = (env_mode, actix) {
+            actix.insert("mode".to_string(), toml::Value::String(mode));
+        }
+        Ok(settings.try_into()?)
+    }
+
+    /// Load the settings from all of their sources, in order of increasing
+    /// precedence:
+    ///
+    /// 1. The `--config` file of `command_line`, or else the file at
+    ///    `default_path`, with its `layers`, like [`parse_layered_toml`].
+    /// 2. The environment variables with the `env_prefix`, like
+    ///    [`apply_env_overrides`], which also applies `port-from-env`.
+    /// 3. The `--set` arguments of `command_line`, like
+    ///    [`apply_command_line`].
+    ///
+    /// [`parse_layered_toml`]: #method.parse_layered_toml
+    /// [`apply_env_overrides`]: #method.apply_env_overrides
+    /// [`apply_command_line`]: #method.apply_command_line
+    pub fn load<P>(
+        command_line: &CommandLine,
+        default_path: P,
+        layers: &Layers,
+        env_prefix: &str,
+    ) -> AtResult<Self>
+    where P: AsRef<Path>,
+          A: serde::Serialize {
+        let mut settings = Self::parse_layered_toml(command_line.config_path(default_path), layers)?;
+        settings.apply_env_overrides(env_prefix)?;
+        settings.apply_command_line(command_line)?;
+        Ok(settings)
     }
 
     /// Parse an instance of `Self` straight from the default `TOML` template.
@@ -130,7 +345,7 @@ private-key = "path/to/cert/key.pem"
         Self::from_template(Self::DEFAULT_TOML_TEMPLATE)
     }
 
-    /// Parse an instance of `Self` straight from the default `TOML` template.
+    /// Parse an instance of `Self` from a `TOML` string.
     pub fn from_template(template: &str) -> AtResult<Self> {
         Ok(toml::from_str::<Self>(template)?)
     }
@@ -173,19 +388,270 @@ private-key = "path/to/cert/key.pem"
             Ok(value) => Self::override_field(field, value),
         }
     }
+
+    /// Override settings with the environment variables whose names start
+    /// with `{prefix}__`, where each further `__` separates the keys of the
+    /// `TOML` tables, e.g. `APP__ACTIX__NUM_WORKERS` overrides
+    /// `actix.num-workers` for the `prefix` `"APP"`.  Fields of `actix` are
+    /// parsed with their [`Parse`] impls, where a `_` in a name stands for
+    /// a `-` in the key.  Values in `application` are parsed as `TOML`
+    /// values, or else taken as strings, and the result must deserialize
+    /// into `A`.  Variables that match neither are ignored.  Afterwards,
+    /// the `port-from-env` setting is applied with
+    /// [`ActixSettings::apply_port_from_env()`], if any.
+    ///
+    /// [`Parse`]: ./trait.Parse.html
+    /// [`ActixSettings::apply_port_from_env()`]: ./struct.ActixSettings.html#method.apply_port_from_env
+    pub fn apply_env_overrides(&mut self, prefix: &str) -> AtResult<()>
+    where A: serde::Serialize {
+        self.apply_overrides(overrides::env_overrides(prefix)?)
+    }
+
+    /// Like [`apply_env_overrides()`], but fail with
+    /// [`AtError::UnknownEnvVars`] without overriding anything if any
+    /// variable with the `prefix` doesn't name a setting, e.g. because of a
+    /// typo like `APP__ACTIX__NUM_WORKER`.  Each of them comes with the
+    /// name of the variable it's closest to, if any is close enough.  Values
+    /// in `application` must already exist in `A` to be known, so optional
+    /// values that are unset can't be overridden in strict mode.
+    ///
+    /// [`apply_env_overrides()`]: #method.apply_env_overrides
+    /// [`AtError::UnknownEnvVars`]: ./enum.AtError.html#variant.UnknownEnvVars
+    pub fn apply_env_overrides_strict(&mut self, prefix: &str) -> AtResult<()>
+    where A: serde::Serialize {
+        let env_overrides = overrides::env_overrides(prefix)?;
+        let application = toml::Value::try_from(&self.application)?;
+        let is_known = |target: &overrides::Target| match target {
+            overrides::Target::Actix(key) => fields::ACTIX_KEYS.contains(&key.as_str()),
+            overrides::Target::Application(path) =>
+                overrides::get_toml_path(&application, path).is_some(),
+            overrides::Target::Unknown => false,
+        };
+        let unknown: Vec<_> = env_overrides.iter()
+            .filter(|env_override| !is_known(&env_override.target))
+            .collect();
+        if !unknown.is_empty() {
+            let known: Vec<String> = fields::ACTIX_KEYS.iter()
+                .map(|key| key.split('.').collect::<Vec<_>>())
+                .map(|path| overrides::env_var_name(&format!("{}__ACTIX", prefix), &path))
+                .chain(overrides::toml_paths(&application).into_iter()
+                    .map(|path| overrides::env_var_name(&format!("{}__APPLICATION", prefix), &path)))
+                .collect();
+            return Err(AtError::UnknownEnvVars(unknown.into_iter()
+                .map(|env_override| (
+                    env_override.name.clone(),
+                    overrides::suggestion(&env_override.name, &known).map(str::to_string),
+                ))
+                .collect()));
+        }
+        self.apply_overrides(env_overrides)
+    }
+
+    fn apply_overrides(&mut self, env_overrides: Vec<overrides::EnvOverride>) -> AtResult<()>
+    where A: serde::Serialize {
+        let mut application: Option<toml::Value> = None;
+        for env_override in env_overrides {
+            match env_override.target {
+                overrides::Target::Actix(key) => {
+                    let value = &env_override.value;
+                    if let Some(result) = fields::set_actix_field(&mut self.actix, &key, value) {
+                        result?;
+                    }
+                },
+                overrides::Target::Application(path) => {
+                    let table = match &mut application {
+                        Some(table) => table,
+                        None => application.insert(toml::Value::try_from(&self.application)?),
+                    };
+                    let value = overrides::toml_value(&env_override.value);
+                    overrides::set_toml_path(table, &path, value)?;
+                },
+                overrides::Target::Unknown => {},
+            }
+        }
+        if let Some(application) = application {
+            self.application = application.try_into()?;
+        }
+        self.actix.apply_port_from_env()
+    }
+
+    /// Set the setting at the dotted `path` of its `TOML` key, e.g.
+    /// `"actix.keep-alive"` or `"application.foo.bar"`, to `value`.  Fields
+    /// of `actix` are parsed with their [`Parse`] impls.  Values in
+    /// `application` are parsed as `TOML` values, or else taken as strings,
+    /// and the result must deserialize into `A`.  Fails with
+    /// [`AtError::UnknownSetting`] if `path` doesn't name a setting, which
+    /// includes values in `application` that aren't present in `A`.
+    /// Setting `"actix.port-from-env"` applies it right away, so a later
+    /// `set()` of `"actix.hosts"` takes precedence.
+    ///
+    /// [`Parse`]: ./trait.Parse.html
+    /// [`AtError::UnknownSetting`]: ./enum.AtError.html#variant.UnknownSetting
+    pub fn set(&mut self, path: &str, value: &str) -> AtResult<()>
+    where A: serde::Serialize {
+        let unknown = || AtError::UnknownSetting(path.to_string());
+        match path.split_once('.') {
+            Some(("actix", "port-from-env")) => {
+                fields::set_actix_field(&mut self.actix, "port-from-env", value)
+                    .ok_or_else(unknown)??;
+                self.actix.apply_port_from_env()
+            },
+            Some(("actix", key)) => fields::set_actix_field(&mut self.actix, key, value)
+                .ok_or_else(unknown)?,
+            Some(("application", key)) => {
+                let path: Vec<String> = key.split('.').map(str::to_string).collect();
+                let mut application = toml::Value::try_from(&self.application)?;
+                if overrides::get_toml_path(&application, &path).is_none() {
+                    return Err(unknown());
+                }
+                overrides::set_toml_path(&mut application, &path, overrides::toml_value(value))?;
+                self.application = application.try_into()?;
+                Ok(())
+            },
+            _ => Err(unknown()),
+        }
+    }
+
+    /// The `TOML` value of the setting at the dotted `path` of its key, e.g.
+    /// `"actix.ssl.enabled"`, or `None` for an `actix` setting that's unset,
+    /// such as `"actix.port-file"` by default.  Paths may name tables too,
+    /// e.g. `"actix.ssl"`.  Fails with [`AtError::UnknownSetting`] if `path`
+    /// doesn't name a setting.
+    ///
+    /// [`AtError::UnknownSetting`]: ./enum.AtError.html#variant.UnknownSetting
+    pub fn get(&self, path: &str) -> AtResult<Option<toml::Value>>
+    where A: serde::Serialize {
+        let unknown = || AtError::UnknownSetting(path.to_string());
+        let (table, key) = match path.split_once('.') {
+            Some(("actix", key)) => (toml::Value::try_from(&self.actix)?, key),
+            Some(("application", key)) => (toml::Value::try_from(&self.application)?, key),
+            _ => return Err(unknown()),
+        };
+        let segments: Vec<&str> = key.split('.').collect();
+        match overrides::get_toml_path(&table, &segments) {
+            Some(value) => Ok(Some(value.clone())),
+            // Unset optional fields are left out of the serialized `actix`:
+            None if path.starts_with("actix.") && fields::ACTIX_KEYS.contains(&key) =>
+                Ok(None),
+            None => Err(unknown()),
+        }
+    }
+
+    /// The dotted paths of the keys that [`set()`] accepts: Every field of
+    /// `actix` that can be parsed from a string, followed by the values in
+    /// `application`.
+    ///
+    /// [`set()`]: #method.set
+    pub fn keys(&self) -> AtResult<Vec<String>>
+    where A: serde::Serialize {
+        let application = toml::Value::try_from(&self.application)?;
+        Ok(fields::ACTIX_KEYS.iter()
+            .map(|key| format!("actix.{}", key))
+            .chain(overrides::toml_paths(&application).into_iter()
+                .filter(|path| !path.is_empty())
+                .map(|path| format!("application.{}", path.join("."))))
+            .collect())
+    }
+
+    /// Apply the `--set` arguments of `command_line` with [`set()`], in
+    /// order.  To let them override everything else, apply them after
+    /// reading the `--config` file and applying any environment overrides.
+    ///
+    /// [`set()`]: #method.set
+    pub fn apply_command_line(&mut self, command_line: &CommandLine) -> AtResult<()>
+    where A: serde::Serialize {
+        for (key, value) in &command_line.set {
+            self.set(key, value)?;
+        }
+        Ok(())
+    }
+
+    /// Like [`apply_command_line()`], but on failure, return the message of
+    /// [`CommandLine::usage()`], which lists the valid keys if a key is
+    /// unknown.  Whether to print it and exit is left to the caller.
+    ///
+    /// [`apply_command_line()`]: #method.apply_command_line
+    /// [`CommandLine::usage()`]: ./struct.CommandLine.html#method.usage
+    pub fn apply_command_line_or_usage(&mut self, command_line: &CommandLine) -> Result<(), String>
+    where A: serde::Serialize {
+        self.apply_command_line(command_line).map_err(|error| {
+            let keys = self.keys().unwrap_or_default();
+            command_line.usage(&error, &keys)
+        })
+    }
+
+    /// Check whether every listener can be bound, by binding and
+    /// immediately releasing each of its addresses.  This never panics:
+    /// Each problem, such as a port in use, a privileged port, or a host
+    /// that isn't a local address, is reported in the returned report.
+    /// TLS settings aren't checked.
+    pub fn preflight(&self) -> PreflightReport {
+        preflight::preflight(&self.actix)
+    }
+
+    /// Drop the privileges of the process as configured by
+    /// `[actix.process]`, if at all: Change the root directory, and then
+    /// switch to its group and user.  Call this after the last socket has
+    /// been bound, e.g. by [`ApplySettings`], and before running the server,
+    /// as its workers only start handling requests then.
+    ///
+    /// [`ApplySettings`]: ./trait.ApplySettings.html
+    pub fn drop_privileges(&self) -> AtResult<()> {
+        #[cfg(unix)]
+        process::drop_privileges(&self.actix.process)?;
+        #[cfg(not(unix))]
+        if self.actix.process != ProcessSettings::default() {
+            return Err(InvalidValue! {
+                expected: "no [actix.process] settings on platforms other than Unix",
+                got: format!("{:?}", self.actix.process),
+            });
+        }
+        Ok(())
+    }
 }
 
 
 
+/// The backlog `HttpServer` uses unless configured otherwise.
+const DEFAULT_BACKLOG: i32 = 1024;
+
+
 pub trait ApplySettings {
     #[must_use]
     /// Apply a [`BasicSettings`] value to `self`.
     ///
+    /// Panics if any of the addresses can't be bound, or if the TLS setup
+    /// fails.  See [`TryApplySettings`] for a fallible alternative.
+    ///
     /// [`BasicSettings`]: ./struct.BasicSettings.html
+    /// [`TryApplySettings`]: ./trait.TryApplySettings.html
     fn apply_settings<A>(self, settings: &BasicSettings<A>) -> Self
     where A: for<'de> serde::de::Deserialize<'de>;
 }
 
+pub trait TryApplySettings: Sized {
+    /// Apply a [`BasicSettings`] value to `self`, returning an error
+    /// rather than panicking if an address can't be bound or the
+    /// TLS setup fails.
+    ///
+    /// [`BasicSettings`]: ./struct.BasicSettings.html
+    fn try_apply_settings<A>(self, settings: &BasicSettings<A>) -> AtResult<Self>
+    where A: for<'de> serde::de::Deserialize<'de> {
+        self.try_apply_settings_and_report(settings).map(|(this, _)| this)
+    }
+
+    /// Like [`try_apply_settings`], but also report the addresses that were
+    /// actually bound for each listener e.g. the port picked by the OS for
+    /// port 0.  These are also written to the `port-file`, if configured.
+    ///
+    /// [`try_apply_settings`]: #method.try_apply_settings
+    fn try_apply_settings_and_report<A>(
+        self,
+        settings: &BasicSettings<A>,
+    ) -> AtResult<(Self, BoundListeners)>
+    where A: for<'de> serde::de::Deserialize<'de>;
+}
+
 impl<F, I, S, B> ApplySettings for HttpServer<F, I, S, B>
 where
     F: Fn() -> I + Send + Clone + 'static,
@@ -197,19 +663,178 @@ where
     <S::Service as Service>::Future: 'static,
     B: MessageBody + 'static
 {
-    fn apply_settings<A>(mut self, settings: &BasicSettings<A>) -> Self
+    fn apply_settings<A>(self, settings: &BasicSettings<A>) -> Self
     where A: for<'de> serde::de::Deserialize<'de> {
-        if settings.actix.ssl.enabled {
-            // for Address { host, port } in &settings.actix.hosts {
-            //     self = self.bind(format!("{}:{}", host, port))
-            //         .unwrap(/*TODO*/);
-            // }
-            todo!("[ApplySettings] SSL support has not been implemented yet.");
-        } else {
-            for Address { host, port } in &settings.actix.hosts {
-                self = self.bind(format!("{}:{}", host, port))
-                    .unwrap(/*TODO*/);
+        self.try_apply_settings(settings)
+            .unwrap_or_else(|err| panic!("[ApplySettings] {:?}", err))
+    }
+}
+
+impl<F, I, S, B> TryApplySettings for HttpServer<F, I, S, B>
+where
+    F: Fn() -> I + Send + Clone + 'static,
+    I: IntoServiceFactory<S>,
+    S: ServiceFactory<Config = AppConfig, Request = Request>,
+    S::Error: Into<WebError> + 'static,
+    S::InitError: Debug,
+    S::Response: Into<Response<B>> + 'static,
+    <S::Service as Service>::Future: 'static,
+    B: MessageBody + 'static
+{
+    fn try_apply_settings_and_report<A>(
+        mut self,
+        settings: &BasicSettings<A>,
+    ) -> AtResult<(Self, BoundListeners)>
+    where A: for<'de> serde::de::Deserialize<'de> {
+        let bind_error = |address: &ListenAddress| {
+            let address = address.clone();
+            move |err| AtError::BindError { address, error: IoError::from(err) }
+        };
+        let listeners = settings.actix.all_listeners();
+        #[cfg(any(feature = "rustls", feature = "openssl"))]
+        if listeners.iter().any(|listener| listener.tls) {
+            tls::auto_generate_certificate(&settings.actix)?;
+        }
+        let mut acceptors = tls::Acceptors::default();
+        let mut bound = BoundListeners::default();
+        // Serves a `TcpListener` with the TLS setup of `listener`, if any:
+        macro_rules! listen_tcp {
+            ($listener:expr, $socket:expr) => {
+                if $listener.tls {
+                    let ssl = $listener.ssl(&settings.actix.ssl)?;
+                    match acceptors.acceptor(&ssl)? {
+                        #[cfg(feature = "rustls")]
+                        tls::Acceptor::Rustls(config) => self.listen_rustls($socket, config),
+                        #[cfg(feature = "openssl")]
+                        tls::Acceptor::Openssl(builder) => self.listen_openssl($socket, builder),
+                    }
+                } else {
+                    self.listen($socket)
+                }
+            };
+        }
+        // The inherited sockets are read from the environment only once, and
+        // only if any listener is socket-activated:
+        #[cfg(unix)]
+        let mut activated: Option<systemd::ActivatedSockets> = None;
+        #[cfg(unix)]
+        let claimed: HashSet<&str> = listeners.iter()
+            .filter(|listener| matches!(listener.address, ListenAddress::Activation(_)))
+            .filter_map(|listener| listener.fd_name.as_deref())
+            .collect();
+        for listener in &listeners {
+            listener.validate()?;
+            let mut addrs = vec![];
+            let address = match &listener.address {
+                ListenAddress::Tcp(address) => address.clone(),
+                #[cfg(unix)]
+                ListenAddress::Unix(path) => {
+                    let socket = unix::bind(listener, path)?;
+                    self = self.listen_uds(socket).map_err(bind_error(&listener.address))?;
+                    addrs.push(BoundAddress::Unix(path.clone()));
+                    bound.0.push(BoundListener { listener: listener.clone(), addrs });
+                    continue;
+                },
+                #[cfg(unix)]
+                ListenAddress::Activation(Activation::Systemd) => {
+                    if activated.is_none() {
+                        activated = Some(systemd::ActivatedSockets::from_env()?);
+                    }
+                    let activated = activated.as_mut().unwrap();
+                    for socket in activated.take(listener.fd_name.as_deref(), &claimed)? {
+                        let local_addr = match &socket {
+                            systemd::ActivatedSocket::Tcp(socket) =>
+                                socket.local_addr().map(BoundAddress::Tcp).ok(),
+                            systemd::ActivatedSocket::Unix(socket) => socket.local_addr().ok()
+                                .and_then(|addr| addr.as_pathname().map(Path::to_path_buf))
+                                .map(BoundAddress::Unix),
+                        };
+                        addrs.extend(local_addr);
+                        self = match socket {
+                            systemd::ActivatedSocket::Unix(_) if listener.tls =>
+                                return Err(InvalidValue! {
+                                    expected: "`tls = false` for inherited Unix sockets \
+                                               (actix-web doesn't support TLS over Unix sockets)",
+                                    got: &listener.address,
+                                }),
+                            systemd::ActivatedSocket::Unix(socket) => {
+                                #[cfg(target_os = "linux")]
+                                if let Err(err) = systemd::keep_socket_file(&socket) {
+                                    log::warn!("Can't keep the file of inherited socket {:?} \
+                                                in place: {}", socket.local_addr(), err);
+                                }
+                                self.listen_uds(socket)
+                            },
+                            systemd::ActivatedSocket::Tcp(socket) =>
+                                listen_tcp!(listener, socket),
+                        }.map_err(bind_error(&listener.address))?;
+                    }
+                    bound.0.push(BoundListener { listener: listener.clone(), addrs });
+                    continue;
+                },
+                #[cfg(not(unix))]
+                ListenAddress::Unix(_) | ListenAddress::Activation(_) =>
+                    return Err(InvalidValue! {
+                        expected: "no `unix` or socket-activated listeners \
+                                   on platforms other than Unix",
+                        got: &listener.address,
+                    }),
+            };
+            // NOTE: The backlog is applied when an address is bound, so it
+            //       has to be set before binding each listener.
+            let backlog = match (&listener.backlog, &settings.actix.backlog) {
+                (Backlog::Manual(n), _) | (Backlog::Default, Backlog::Manual(n)) => *n as i32,
+                (Backlog::Default, Backlog::Default) => DEFAULT_BACKLOG,
+            };
+            self = self.backlog(backlog);
+            let socket_addrs = address.resolve(listener.resolve)
+                .map_err(bind_error(&listener.address))?;
+            if let Host::Name(_) = address.host {
+                log::info!("Resolved {} to {:?} (resolve = {:?})",
+                           address, socket_addrs, listener.resolve);
+            }
+            // Sockets with options are created here rather than by `HttpServer`:
+            if listener.socket != SocketOptions::default() {
+                // NOTE: Like `HttpServer::bind()`, this succeeds if any of the
+                //       addresses can be bound, and otherwise fails with the
+                //       last error.
+                let mut last_error = None;
+                for &addr in &socket_addrs {
+                    let socket = match socket::bind(addr, &listener.socket, backlog) {
+                        Ok(socket) => socket,
+                        Err(err) => {
+                            log::warn!("Can't bind {} of {}: {}", addr, address, err);
+                            last_error = Some(err);
+                            continue;
+                        },
+                    };
+                    addrs.extend(socket.local_addr().map(BoundAddress::Tcp));
+                    self = listen_tcp!(listener, socket).map_err(bind_error(&listener.address))?;
+                }
+                if let (true, Some(err)) = (addrs.is_empty(), last_error) {
+                    return Err(bind_error(&listener.address)(err));
+                }
+                bound.0.push(BoundListener { listener: listener.clone(), addrs });
+                continue;
             }
+            let num_addrs = self.addrs().len();
+            self = if listener.tls {
+                let ssl = listener.ssl(&settings.actix.ssl)?;
+                match acceptors.acceptor(&ssl)? {
+                    #[cfg(feature = "rustls")]
+                    tls::Acceptor::Rustls(config) => self.bind_rustls(&socket_addrs[..], config),
+                    #[cfg(feature = "openssl")]
+                    tls::Acceptor::Openssl(builder) => self.bind_openssl(&socket_addrs[..], builder),
+                }
+            } else {
+                self.bind(&socket_addrs[..])
+            }.map_err(bind_error(&listener.address))?;
+            addrs.extend(self.addrs().into_iter().skip(num_addrs).map(BoundAddress::Tcp));
+            bound.0.push(BoundListener { listener: listener.clone(), addrs });
+        }
+        #[cfg(unix)]
+        for name in activated.iter().flat_map(systemd::ActivatedSockets::unused) {
+            log::warn!("Ignoring inherited socket {:?}, which no listener uses", name);
         }
         self = match settings.actix.num_workers {
             NumWorkers::Default   => self,
@@ -248,7 +873,10 @@ where
             Timeout::Milliseconds(_) => self.shutdown_timeout(1),
             Timeout::Seconds(n)      => self.shutdown_timeout(n as u64),
         };
-        self
+        if let Some(port_file) = &settings.actix.port_file {
+            bound.write_port_file(port_file)?;
+        }
+        Ok((self, bound))
     }
 }
 
@@ -259,53 +887,1130 @@ mod tests {
     #![allow(non_snake_case)]
 
     use actix_web::{App, HttpServer};
-    use crate::{ApplySettings, AtResult, BasicSettings, Settings};
+    use crate::{
+        ApplySettings, ArrayMerge, AtError, AtResult, BasicSettings, BoundAddress, CommandLine, Layers, Parse,
+        PreflightStatus, Settings, TableMerge, TryApplySettings,
+    };
     use crate::actix::*; // used for value construction in assertions
+    use crate::testing;
     use serde::Deserialize;
-    use std::path::Path;
+    use std::path::{Path, PathBuf};
 
     #[test]
     fn apply_settings() -> AtResult<()> {
-        let settings = Settings::parse_toml("Server.toml")?;
+        let dir = tempfile::tempdir()?;
+        let settings = Settings::parse_toml(dir.path().join("Server.toml"))?;
         let _ = HttpServer::new(|| { App::new() })
             .apply_settings(&settings);
         Ok(())
     }
 
+    #[test]
+    fn try_apply_settings__address_in_use() -> AtResult<()> {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0")?;
+        let port = listener.local_addr()?.port();
+        let mut settings = Settings::from_default_template()?;
+        let address = Address { host: Host::parse("127.0.0.1")?, port };
+        settings.actix.hosts = vec![Endpoint::Address(address.clone())];
+        match HttpServer::new(|| { App::new() }).try_apply_settings(&settings) {
+            Err(AtError::BindError { address: error_address, error }) => {
+                assert_eq!(error_address, ListenAddress::Tcp(address));
+                assert_eq!(error.kind(), ioe::IoErrKind::AddrInUse);
+            },
+            Err(err) => panic!("expected AtError::BindError, got {:?}", err),
+            Ok(_) => panic!("expected AtError::BindError"),
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn try_apply_settings__listeners() -> AtResult<()> {
+        let mut settings = Settings::from_default_template()?;
+        settings.actix.hosts = testing::localhost_port_0();
+        settings.actix.listeners = vec![Listener::new(testing::localhost_address().into())];
+        settings.actix.listeners[0].backlog = Backlog::Manual(16);
+        let addrs = testing::spawn_server(settings);
+        assert_eq!(addrs.len(), 2);
+        for addr in addrs {
+            let response = testing::http_get(addr)?;
+            assert!(response.starts_with("HTTP/1.1 200 OK"), "{}", response);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn try_apply_settings_and_report__port_file() -> AtResult<()> {
+        let dir = tempfile::tempdir()?;
+        let port_file = dir.path().join("run").join("ports");
+        let mut settings = Settings::from_default_template()?;
+        settings.actix.hosts = testing::localhost_port_0();
+        settings.actix.listeners = vec![Listener::new(testing::localhost_address().into())];
+        #[cfg(unix)]
+        settings.actix.listeners.push(unix_listener(&dir.path().join("app.sock")));
+        settings.actix.port_file = Some(port_file.clone());
+        let (server, bound) = HttpServer::new(App::new)
+            .try_apply_settings_and_report(&settings)?;
+        assert_eq!(bound.0.len(), settings.actix.all_listeners().len());
+        let tcp_addrs = bound.tcp_addrs();
+        assert_eq!(tcp_addrs.len(), 2);
+        assert!(tcp_addrs.iter().all(|addr| addr.port() != 0), "{:?}", tcp_addrs);
+        assert_eq!(&server.addrs()[..2], &tcp_addrs[..]);
+        let mut expected = format!("http://{}\nhttp://{}\n", tcp_addrs[0], tcp_addrs[1]);
+        #[cfg(unix)]
+        expected.push_str(&format!("unix://{}\n", dir.path().join("app.sock").display()));
+        assert_eq!(std::fs::read_to_string(&port_file)?, expected);
+        assert_eq!(bound.urls(), expected);
+        Ok(())
+    }
+
+    #[test]
+    fn preflight() -> AtResult<()> {
+        let mut settings = Settings::from_default_template()?;
+        settings.actix.hosts = testing::localhost_port_0();
+        settings.actix.listeners = vec![Listener::new(ListenAddress::Activation(Activation::Systemd))];
+        let report = settings.preflight();
+        assert!(report.is_ok(), "{}", report);
+        assert_eq!(report.0.len(), 2);
+        assert!(matches!(report.0[0].status, PreflightStatus::Ok));
+        assert!(matches!(report.0[1].status, PreflightStatus::Skipped));
+        Ok(())
+    }
+
+    #[test]
+    fn preflight__failures() -> AtResult<()> {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0")?;
+        let port = listener.local_addr()?.port();
+        let mut settings = Settings::from_default_template()?;
+        settings.actix.hosts = vec![
+            Endpoint::Address(Address { host: Host::parse("127.0.0.1")?, port }),
+            // An address from TEST-NET-1, which no interface should have:
+            Endpoint::Address(Address { host: Host::parse("192.0.2.1")?, port: 0 }),
+        ];
+        settings.actix.listeners = vec![Listener {
+            fd_name: Some("http".into()),
+            ..Listener::new(testing::localhost_address().into())
+        }];
+        let report = settings.preflight();
+        assert!(!report.is_ok());
+        assert_eq!(report.failures().count(), 3, "{}", report);
+        assert!(matches!(report.0[0].status, PreflightStatus::AddressInUse), "{}", report);
+        assert!(matches!(report.0[1].status, PreflightStatus::NotLocal), "{}", report);
+        assert!(matches!(report.0[2].status, PreflightStatus::Invalid(_)), "{}", report);
+        assert_eq!(report.0[0].addr, Some(BoundAddress::Tcp(listener.local_addr()?)));
+        assert_eq!(report.0[2].addr, None);
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn preflight__unix_listener() -> AtResult<()> {
+        let dir = tempfile::tempdir()?;
+        let free = dir.path().join("free.sock");
+        let in_use = dir.path().join("in-use.sock");
+        let _socket = std::os::unix::net::UnixListener::bind(&in_use)?;
+        let mut settings = Settings::from_default_template()?;
+        settings.actix.hosts = vec![];
+        settings.actix.listeners = vec![unix_listener(&free), unix_listener(&in_use)];
+        let report = settings.preflight();
+        assert!(matches!(report.0[0].status, PreflightStatus::Ok), "{}", report);
+        assert!(matches!(report.0[1].status, PreflightStatus::AddressInUse), "{}", report);
+        assert!(!free.exists());
+        assert_eq!(std::fs::read_dir(dir.path())?.count(), 1);
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    fn unix_listener(path: &Path) -> Listener {
+        Listener::new(ListenAddress::Unix(path.to_path_buf()))
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn try_apply_settings__unix_listener() -> AtResult<()> {
+        use std::os::unix::fs::{MetadataExt, PermissionsExt};
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("app.sock");
+        let (uid, gid) = unsafe { (libc::getuid(), libc::getgid()) };
+        let mut settings = Settings::from_default_template()?;
+        settings.actix.hosts = vec![];
+        settings.actix.listeners = vec![Listener {
+            mode: Some(SocketMode(0o640)),
+            owner: Some(SocketOwner { user: Some(uid.to_string()), group: Some(gid.to_string()) }),
+            ..unix_listener(&path)
+        }];
+        testing::spawn_server(settings);
+        let metadata = std::fs::metadata(&path)?;
+        assert_eq!(metadata.permissions().mode() & 0o777, 0o640);
+        assert_eq!((metadata.uid(), metadata.gid()), (uid, gid));
+        let response = testing::http_get_unix(&path)?;
+        assert!(response.starts_with("HTTP/1.1 200 OK"), "{}", response);
+        Ok(())
+    }
+
+    /// Serves a Unix listener under load, as run by
+    /// `try_apply_settings__unix_listener_under_load`, since `max-connections`
+    /// applies to every server in the process.  Does nothing when run as part
+    /// of the test suite.
+    #[cfg(unix)]
+    #[test]
+    #[ignore]
+    fn unix_listener_under_load() -> AtResult<()> {
+        if std::env::var_os("ACTIX_SETTINGS_UNDER_LOAD").is_none() { return Ok(()); }
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("app.sock");
+        let mut settings = Settings::from_default_template()?;
+        settings.actix.hosts = vec![];
+        settings.actix.listeners = vec![unix_listener(&path)];
+        settings.actix.num_workers = NumWorkers::Manual(1);
+        settings.actix.max_connections = MaxConnections::Manual(1);
+        testing::spawn_server(settings);
+        let response = testing::http_get_unix(&path)?;
+        assert!(response.starts_with("HTTP/1.1 200 OK"), "{}", response);
+        // An idle connection takes the only connection of the only worker,
+        // which then queues the next one and becomes unavailable, so that
+        // actix-server stops accepting connections on the third one:
+        let idle = std::os::unix::net::UnixStream::connect(&path)?;
+        let queued = testing::spawn_http_get_unix(&path);
+        let accepted_under_backpressure = testing::spawn_http_get_unix(&path);
+        assert!(path.exists());
+        drop(idle);
+        for handle in [queued, accepted_under_backpressure] {
+            let response = handle.join().expect("Failed to join a request")?;
+            assert!(response.starts_with("HTTP/1.1 200 OK"), "{}", response);
+        }
+        let response = testing::http_get_unix(&path)?;
+        assert!(response.starts_with("HTTP/1.1 200 OK"), "{}", response);
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn try_apply_settings__unix_listener_under_load() -> AtResult<()> {
+        testing::run_in_child("tests::unix_listener_under_load", &[
+            ("ACTIX_SETTINGS_UNDER_LOAD", "1".as_ref()),
+        ])?;
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn try_apply_settings__stale_socket() -> AtResult<()> {
+        use std::os::unix::net::UnixListener;
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("app.sock");
+        let apply = |stale_socket| {
+            let mut settings = Settings::from_default_template()?;
+            settings.actix.hosts = vec![];
+            settings.actix.listeners = vec![Listener { stale_socket, ..unix_listener(&path) }];
+            HttpServer::new(App::new).try_apply_settings(&settings).map(drop)
+        };
+        let assert_addr_in_use = |result: AtResult<()>| match result {
+            Err(AtError::BindError { error, .. }) =>
+                assert_eq!(error.kind(), ioe::IoErrKind::AddrInUse),
+            other => panic!("expected AtError::BindError, got {:?}", other),
+        };
+        // A socket that nobody accepts connections on anymore:
+        drop(UnixListener::bind(&path)?);
+        assert_addr_in_use(apply(StaleSocket::Keep));
+        apply(StaleSocket::Remove)?;
+
+        // A socket that is still in use:
+        std::fs::remove_file(&path)?;
+        let _live = UnixListener::bind(&path)?;
+        assert_addr_in_use(apply(StaleSocket::Remove));
+        apply(StaleSocket::Force)?;
+
+        // Files other than sockets are never removed:
+        std::fs::remove_file(&path)?;
+        std::fs::write(&path, "not a socket")?;
+        assert_addr_in_use(apply(StaleSocket::Force));
+        assert_eq!(std::fs::read_to_string(&path)?, "not a socket");
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn try_apply_settings__unknown_socket_owner() -> AtResult<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("app.sock");
+        let mut settings = Settings::from_default_template()?;
+        settings.actix.hosts = vec![];
+        settings.actix.listeners = vec![Listener {
+            owner: Some(SocketOwner::parse("no-such-user-for-actix-settings")?),
+            ..unix_listener(&path)
+        }];
+        match HttpServer::new(App::new).try_apply_settings(&settings) {
+            Err(AtError::UnknownUser(name)) =>
+                assert_eq!(name, "no-such-user-for-actix-settings"),
+            Err(err) => panic!("expected AtError::UnknownUser, got {:?}", err),
+            Ok(_) => panic!("expected AtError::UnknownUser"),
+        }
+        assert!(!path.exists());
+        Ok(())
+    }
+
+    /// Serves the sockets passed by `try_apply_settings__systemd_activation`
+    /// until it's terminated.  Does nothing when run as part of the test suite.
+    #[cfg(unix)]
+    #[test]
+    #[ignore]
+    fn socket_activated_server() -> AtResult<()> {
+        if std::env::var_os("ACTIX_SETTINGS_SOCKET_ACTIVATED").is_none() { return Ok(()); }
+        std::env::set_var("LISTEN_PID", std::process::id().to_string());
+        let mut settings = Settings::from_default_template()?;
+        settings.actix.hosts = vec![];
+        Settings::override_field(&mut settings.actix.listeners, r#"[
+            { activation = "systemd", fd-name = "http" },
+            { activation = "systemd" },
+        ]"#)?;
+        settings.actix.num_workers = NumWorkers::Manual(1);
+        settings.actix.max_connections = MaxConnections::Manual(1);
+        let mut sys = actix_rt::System::new("socket-activated-server");
+        let server = HttpServer::new(|| {
+            App::new().route("/", actix_web::web::get().to(|| async { "OK" }))
+        })
+            .try_apply_settings(&settings)?
+            .run();
+        testing::report("READY")?;
+        // The server stops on SIGTERM, and then so does the system:
+        sys.block_on(server)?;
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn try_apply_settings__systemd_activation() -> AtResult<()> {
+        use std::os::unix::io::AsRawFd;
+        use std::os::unix::net::UnixListener;
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("app.sock");
+        let http = std::net::TcpListener::bind("127.0.0.1:0")?;
+        let other = std::net::TcpListener::bind("127.0.0.1:0")?;
+        let unix = UnixListener::bind(&path)?;
+        let mut child = testing::spawn_socket_activated("tests::socket_activated_server", &[
+            ("other", other.as_raw_fd()),
+            ("http", http.as_raw_fd()),
+            ("unix", unix.as_raw_fd()),
+        ])?;
+        let responses = (
+            testing::http_get(http.local_addr()?),
+            testing::http_get(other.local_addr()?),
+            testing::http_get_unix(&path),
+        );
+        // The server has a single worker with a single connection, so the
+        // third connection below is accepted under backpressure:
+        let idle = std::os::unix::net::UnixStream::connect(&path)?;
+        let queued = testing::spawn_http_get_unix(&path);
+        let accepted_under_backpressure = testing::spawn_http_get_unix(&path);
+        let socket_file_exists = path.exists();
+        drop(idle);
+        let responses_under_load = (
+            queued.join().expect("Failed to join a request"),
+            accepted_under_backpressure.join().expect("Failed to join a request"),
+            testing::http_get_unix(&path),
+        );
+        unsafe { libc::kill(child.id() as libc::pid_t, libc::SIGTERM) };
+        assert!(child.wait()?.success());
+        assert!(socket_file_exists);
+        // The socket file is left for the service manager, without the link
+        // that kept it in place:
+        assert!(path.exists());
+        assert_eq!(std::fs::read_dir(dir.path())?.count(), 1);
+        for response in &[
+            responses.0?, responses.1?, responses.2?,
+            responses_under_load.0?, responses_under_load.1?, responses_under_load.2?,
+        ] {
+            assert!(response.starts_with("HTTP/1.1 200 OK"), "{}", response);
+        }
+        Ok(())
+    }
+
+    /// Drops its privileges as configured by
+    /// `drop_privileges`, and then reports its identity.
+    /// Does nothing when run as part of the test suite.
+    #[cfg(unix)]
+    #[test]
+    #[ignore]
+    fn privileges_dropped_server() -> AtResult<()> {
+        let chroot = match std::env::var_os("ACTIX_SETTINGS_DROP_PRIVILEGES") {
+            Some(chroot) => PathBuf::from(chroot),
+            None => return Ok(()),
+        };
+        let mut settings = Settings::from_default_template()?;
+        settings.actix.hosts = testing::localhost_port_0();
+        settings.actix.process = ProcessSettings {
+            user: Some("nobody".into()),
+            group: None,
+            chroot: Some(chroot),
+        };
+        let _server = HttpServer::new(App::new).try_apply_settings(&settings)?;
+        settings.drop_privileges()?;
+        let mut groups = [0; 16];
+        let (uid, gid, euid, num_groups) = unsafe {(
+            libc::getuid(), libc::getgid(), libc::geteuid(),
+            libc::getgroups(groups.len() as libc::c_int, groups.as_mut_ptr()),
+        )};
+        testing::report(&format!("uid={} euid={} gid={} groups={:?} marker={}",
+                                 uid, euid, gid, &groups[..num_groups.max(0) as usize],
+                                 Path::new("/marker").exists()))?;
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn drop_privileges() -> AtResult<()> {
+        use std::os::unix::fs::PermissionsExt;
+        if unsafe { libc::geteuid() } != 0 {
+            eprintln!("Skipping drop_privileges, which requires root");
+            return Ok(());
+        }
+        let dir = tempfile::tempdir()?;
+        std::fs::set_permissions(dir.path(), std::fs::Permissions::from_mode(0o755))?;
+        std::fs::write(dir.path().join("marker"), "")?;
+        let uid = crate::unix::user_id("nobody")?;
+        let gid = crate::unix::user_group_id("nobody")?;
+        let reported = testing::run_in_child("tests::privileges_dropped_server", &[
+            ("ACTIX_SETTINGS_DROP_PRIVILEGES", dir.path().as_os_str()),
+        ])?;
+        assert_eq!(reported, format!("uid={} euid={} gid={} groups=[{}] marker=true\n",
+                                     uid, uid, gid, gid));
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn drop_privileges__unknown_user() -> AtResult<()> {
+        let mut settings = Settings::from_default_template()?;
+        settings.actix.hosts = testing::localhost_port_0();
+        Settings::override_field(&mut settings.actix.process.user, "no-such-user-4a7b")?;
+        let _server = HttpServer::new(App::new).try_apply_settings(&settings)?;
+        match settings.drop_privileges() {
+            Err(AtError::UnknownUser(name)) => assert_eq!(name, "no-such-user-4a7b"),
+            Err(err) => panic!("expected AtError::UnknownUser, got {:?}", err),
+            Ok(_) => panic!("expected AtError::UnknownUser"),
+        }
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn try_apply_settings__systemd_activation_without_sockets() -> AtResult<()> {
+        // NOTE: Other tests don't set `LISTEN_PID`, and this process isn't
+        //       socket-activated, so `LISTEN_PID` is either unset or wrong.
+        let mut settings = Settings::from_default_template()?;
+        settings.actix.hosts = vec![];
+        settings.actix.listeners = vec![Listener::new(ListenAddress::Activation(Activation::Systemd))];
+        match HttpServer::new(App::new).try_apply_settings(&settings) {
+            Err(AtError::SocketActivationError(_)) => {},
+            Err(err) => panic!("expected AtError::SocketActivationError, got {:?}", err),
+            Ok(_) => panic!("expected AtError::SocketActivationError"),
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn listener__validate() -> AtResult<()> {
+        let tcp = Listener::new(testing::localhost_address().into());
+        let unix = Listener {
+            mode: Some(SocketMode(0o660)),
+            ..Listener::new(ListenAddress::Unix("app.sock".into()))
+        };
+        let activated = Listener {
+            fd_name: Some("http".into()),
+            ..Listener::new(ListenAddress::Activation(Activation::Systemd))
+        };
+        let nodelay = SocketOptions { nodelay: Some(true), ..SocketOptions::default() };
+        tcp.validate()?;
+        unix.validate()?;
+        activated.validate()?;
+        Listener { socket: nodelay.clone(), ..tcp.clone() }.validate()?;
+        for invalid in &[
+            Listener {
+                socket: SocketOptions {
+                    keepalive_idle: Timeout::Milliseconds(1500),
+                    ..SocketOptions::default()
+                },
+                ..tcp.clone()
+            },
+            Listener { socket: nodelay.clone(), ..unix.clone() },
+            Listener { resolve: Resolve::Ipv4, ..unix.clone() },
+            Listener { socket: nodelay, ..activated.clone() },
+            Listener { mode: Some(SocketMode(0o660)), ..tcp.clone() },
+            Listener { owner: Some(SocketOwner::parse("www-data")?), ..tcp.clone() },
+            Listener { fd_name: Some("http".into()), ..tcp },
+            Listener { tls: true, ..unix.clone() },
+            Listener { backlog: Backlog::Manual(16), ..unix },
+            Listener { backlog: Backlog::Manual(16), ..activated.clone() },
+            Listener { mode: Some(SocketMode(0o660)), ..activated },
+        ] {
+            match invalid.validate() {
+                Err(AtError::InvalidValue { .. }) => {},
+                other => panic!("expected AtError::InvalidValue, got {:?}", other),
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn override_field__listeners__unix() -> AtResult<()> {
+        let mut settings = Settings::from_default_template()?;
+        Settings::override_field(&mut settings.actix.listeners, r#"[{
+            unix = "/run/app.sock", mode = "0660", owner = "www-data:www-data",
+            stale-socket = "keep"
+        }]"#.replace('\n', " "))?;
+        assert_eq!(settings.actix.listeners.len(), 1);
+        let listener = &settings.actix.listeners[0];
+        assert_eq!(listener.address, ListenAddress::Unix("/run/app.sock".into()));
+        assert_eq!(listener.mode, Some(SocketMode(0o660)));
+        assert_eq!(listener.owner, Some(SocketOwner {
+            user: Some("www-data".into()),
+            group: Some("www-data".into()),
+        }));
+        assert_eq!(listener.stale_socket, StaleSocket::Keep);
+        Ok(())
+    }
+
+    #[test]
+    fn override_field__listeners__activation() -> AtResult<()> {
+        let mut settings = Settings::from_default_template()?;
+        Settings::override_field(&mut settings.actix.listeners, r#"[
+            { activation = "systemd", fd-name = "https", tls = true },
+        ]"#)?;
+        assert_eq!(settings.actix.listeners.len(), 1);
+        let listener = &settings.actix.listeners[0];
+        assert_eq!(listener.address, ListenAddress::Activation(Activation::Systemd));
+        assert_eq!(listener.fd_name.as_deref(), Some("https"));
+        assert!(listener.tls);
+        Ok(())
+    }
+
+    #[test]
+    fn override_field__listeners__socket_options() -> AtResult<()> {
+        let mut settings = Settings::from_default_template()?;
+        Settings::override_field(&mut settings.actix.listeners, r#"[
+            { address = "0.0.0.0:8080", reuse-port = true, nodelay = true },
+            { address = "0.0.0.0:8081", keepalive-idle = "60 seconds", keepalive-count = 5 },
+        ]"#)?;
+        assert_eq!(settings.actix.listeners.len(), 2);
+        assert_eq!(settings.actix.listeners[0].socket, SocketOptions {
+            reuse_port: Some(true),
+            nodelay: Some(true),
+            ..SocketOptions::default()
+        });
+        assert_eq!(settings.actix.listeners[1].socket, SocketOptions {
+            keepalive_idle: Timeout::Seconds(60),
+            keepalive_count: Some(5),
+            ..SocketOptions::default()
+        });
+        Ok(())
+    }
+
+    #[test]
+    fn try_apply_settings__socket_options() -> AtResult<()> {
+        let mut settings = Settings::from_default_template()?;
+        let mut listener = Listener::new(testing::localhost_address().into());
+        listener.socket = SocketOptions {
+            reuse_address: Some(true),
+            nodelay: Some(true),
+            ..SocketOptions::default()
+        };
+        settings.actix.hosts = testing::localhost_port_0();
+        settings.actix.listeners = vec![listener];
+        let addrs = testing::spawn_server(settings);
+        assert_eq!(addrs.len(), 2);
+        for addr in addrs {
+            let response = testing::http_get(addr)?;
+            assert!(response.starts_with("HTTP/1.1 200 OK"), "{}", response);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn try_apply_settings__socket_options__unbindable_address() -> AtResult<()> {
+        let mut settings = Settings::from_default_template()?;
+        settings.actix.hosts = vec![];
+        let localhost = Address { host: Host::parse("localhost")?, port: 0 };
+        let resolved = localhost.resolve(Resolve::All)?;
+        if resolved.len() < 2 {
+            eprintln!("Skipping try_apply_settings__socket_options__unbindable_address, \
+                       which requires localhost to resolve to several addresses");
+            return Ok(());
+        }
+        // The first address is in use, so that only the others can be bound:
+        let in_use = std::net::TcpListener::bind(resolved[0])?;
+        let port = in_use.local_addr()?.port();
+        settings.actix.listeners = vec![Listener {
+            socket: SocketOptions { nodelay: Some(true), ..SocketOptions::default() },
+            ..Listener::new(Address { port, ..localhost }.into())
+        }];
+        let (_, bound) = HttpServer::new(App::new).try_apply_settings_and_report(&settings)?;
+        let addrs = bound.tcp_addrs();
+        assert_eq!(addrs.len(), resolved.len() - 1, "{:?}", addrs);
+        assert!(!addrs.contains(&in_use.local_addr()?), "{:?}", addrs);
+        // Once none can be bound, binding fails:
+        let _also_in_use = addrs.iter()
+            .map(std::net::TcpListener::bind)
+            .collect::<std::io::Result<Vec<_>>>()?;
+        match HttpServer::new(App::new).try_apply_settings(&settings) {
+            Err(AtError::BindError { address, error }) => {
+                assert_eq!(address, settings.actix.listeners[0].address);
+                assert_eq!(error.kind(), ioe::IoErrKind::AddrInUse);
+            },
+            Err(err) => panic!("expected AtError::BindError, got {:?}", err),
+            Ok(_) => panic!("expected AtError::BindError"),
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn parse__resolve() -> AtResult<()> {
+        assert_eq!(Resolve::parse("all")?, Resolve::All);
+        assert_eq!(Resolve::parse("first")?, Resolve::First);
+        assert_eq!(Resolve::parse("ipv4")?, Resolve::Ipv4);
+        assert_eq!(Resolve::parse("ipv6")?, Resolve::Ipv6);
+        assert!(Resolve::parse("IPv4").is_err());
+        let mut settings = Settings::from_default_template()?;
+        Settings::override_field(&mut settings.actix.listeners, r#"[
+            { address = "localhost:8080", resolve = "ipv4" },
+            { address = "localhost:8081" },
+        ]"#)?;
+        assert_eq!(settings.actix.listeners[0].resolve, Resolve::Ipv4);
+        assert_eq!(settings.actix.listeners[1].resolve, Resolve::All);
+        Ok(())
+    }
+
+    #[test]
+    fn address__resolve() -> AtResult<()> {
+        let localhost = Address { host: Host::parse("localhost")?, port: 8080 };
+        let all = localhost.resolve(Resolve::All)?;
+        let ipv4 = localhost.resolve(Resolve::Ipv4)?;
+        assert!(!ipv4.is_empty() && ipv4.iter().all(|addr| addr.is_ipv4()), "{:?}", ipv4);
+        assert!(ipv4.iter().all(|addr| all.contains(addr)), "{:?} {:?}", ipv4, all);
+        assert_eq!(localhost.resolve(Resolve::First)?, all[..1]);
+        let ip = Address { host: Host::parse("127.0.0.1")?, port: 8080 };
+        assert_eq!(ip.resolve(Resolve::All)?, ["127.0.0.1:8080".parse().unwrap()]);
+        match ip.resolve(Resolve::Ipv6) {
+            Err(err) => assert_eq!(err.kind(), std::io::ErrorKind::AddrNotAvailable),
+            Ok(addrs) => panic!("Expected no IPv6 addresses, got {:?}", addrs),
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn try_apply_settings__resolve() -> AtResult<()> {
+        let mut settings = Settings::from_default_template()?;
+        settings.actix.hosts = vec![];
+        let localhost = Address { host: Host::parse("localhost")?, port: 0 };
+        settings.actix.listeners = vec![
+            Listener { resolve: Resolve::Ipv4, ..Listener::new(localhost.clone().into()) },
+            Listener { resolve: Resolve::Ipv6, ..Listener::new(testing::localhost_address().into()) },
+        ];
+        match HttpServer::new(App::new).try_apply_settings(&settings) {
+            Err(AtError::BindError { address, error }) => {
+                assert_eq!(address, settings.actix.listeners[1].address);
+                assert_eq!(error.kind(), ioe::IoErrKind::AddrNotAvailable);
+            },
+            Err(err) => panic!("expected AtError::BindError, got {:?}", err),
+            Ok(_) => panic!("expected AtError::BindError"),
+        }
+        settings.actix.listeners.truncate(1);
+        let (_, bound) = HttpServer::new(App::new).try_apply_settings_and_report(&settings)?;
+        let addrs = bound.tcp_addrs();
+        assert!(!addrs.is_empty() && addrs.iter().all(|addr| addr.is_ipv4()), "{:?}", addrs);
+        Ok(())
+    }
+
+    #[test]
+    fn try_apply_settings__resolve__socket_options() -> AtResult<()> {
+        let mut settings = Settings::from_default_template()?;
+        settings.actix.hosts = vec![];
+        let localhost = Address { host: Host::parse("localhost")?, port: 0 };
+        let nodelay = SocketOptions { nodelay: Some(true), ..SocketOptions::default() };
+        settings.actix.listeners = vec![
+            Listener {
+                resolve: Resolve::Ipv4,
+                socket: nodelay.clone(),
+                ..Listener::new(localhost.into())
+            },
+            Listener {
+                resolve: Resolve::Ipv6,
+                socket: nodelay,
+                ..Listener::new(testing::localhost_address().into())
+            },
+        ];
+        match HttpServer::new(App::new).try_apply_settings(&settings) {
+            Err(AtError::BindError { address, error }) => {
+                assert_eq!(address, settings.actix.listeners[1].address);
+                assert_eq!(error.kind(), ioe::IoErrKind::AddrNotAvailable);
+            },
+            Err(err) => panic!("expected AtError::BindError, got {:?}", err),
+            Ok(_) => panic!("expected AtError::BindError"),
+        }
+        settings.actix.listeners.truncate(1);
+        let addrs = testing::spawn_server(settings);
+        assert!(!addrs.is_empty() && addrs.iter().all(|addr| addr.is_ipv4()), "{:?}", addrs);
+        for addr in addrs {
+            let response = testing::http_get(addr)?;
+            assert!(response.starts_with("HTTP/1.1 200 OK"), "{}", response);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn parse__socket_mode() -> AtResult<()> {
+        assert_eq!(SocketMode::parse("0660")?, SocketMode(0o660));
+        assert_eq!(SocketMode::parse("0o600")?, SocketMode(0o600));
+        assert_eq!(Option::<SocketMode>::parse("")?, None);
+        for invalid in &["", "0689", "01777", "rw-rw----"] {
+            match SocketMode::parse(invalid) {
+                Err(AtError::InvalidValue { .. }) => {},
+                other => panic!("expected AtError::InvalidValue, got {:?}", other),
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn parse__socket_owner() -> AtResult<()> {
+        let owner = |user: Option<&str>, group: Option<&str>| SocketOwner {
+            user: user.map(Into::into),
+            group: group.map(Into::into),
+        };
+        assert_eq!(SocketOwner::parse("www-data")?, owner(Some("www-data"), None));
+        assert_eq!(SocketOwner::parse("app:www")?, owner(Some("app"), Some("www")));
+        assert_eq!(SocketOwner::parse(":www")?, owner(None, Some("www")));
+        assert_eq!(Option::<SocketOwner>::parse("")?, None);
+        for invalid in &["", ":", "app:"] {
+            match SocketOwner::parse(invalid) {
+                Err(AtError::InvalidValue { .. }) => {},
+                other => panic!("expected AtError::InvalidValue, got {:?}", other),
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn all_listeners() -> AtResult<()> {
+        let settings = Settings::from_template(&(
+            Settings::DEFAULT_TOML_TEMPLATE.replace("enabled = false", "enabled = true")
+                + "\n[[actix.listeners]]"
+                + "\naddress = [\"127.0.0.1\", 8080]"
+                + "\n[[actix.listeners]]"
+                + "\naddress = [\"127.0.0.1\", 8443]"
+                + "\ntls = true"
+                + "\nbacklog = \"64\""
+        ))?;
+        let listener = |host: &str, port, tls, backlog| Listener {
+            tls,
+            backlog,
+            ..Listener::new(Address { host: Host::parse(host).unwrap(), port }.into())
+        };
+        assert_eq!(settings.actix.all_listeners(), vec![
+            listener("0.0.0.0", 9000, true, Backlog::Default),
+            listener("127.0.0.1", 8080, false, Backlog::Default),
+            listener("127.0.0.1", 8443, true, Backlog::Manual(64)),
+        ]);
+        Ok(())
+    }
+
+    #[test]
+    fn all_listeners__urls() -> AtResult<()> {
+        let settings = Settings::from_template(&Settings::DEFAULT_TOML_TEMPLATE.replace(
+            r#"["0.0.0.0", 9000]"#,
+            r#""http://0.0.0.0:8080", "https://0.0.0.0:8443", "unix:///run/app.sock","#,
+        ))?;
+        let tcp = |port, tls| Listener {
+            tls,
+            ..Listener::new(Address { host: Host::Ip([0, 0, 0, 0].into()), port }.into())
+        };
+        assert_eq!(settings.actix.all_listeners(), vec![
+            tcp(8080, false),
+            tcp(8443, true),
+            Listener::new(ListenAddress::Unix("/run/app.sock".into())),
+        ]);
+        Ok(())
+    }
+
+    #[test]
+    fn listener__ssl() -> AtResult<()> {
+        let settings = Settings::from_default_template()?;
+        let mut listener = Listener::new(testing::localhost_address().into());
+        assert_eq!(listener.ssl(&settings.actix.ssl)?, settings.actix.ssl);
+        listener.pkcs12 = Some("listener.p12".into());
+        listener.private_key_passphrase = Some(Secret::Env("LISTENER_PASSPHRASE".into()));
+        let ssl = listener.ssl(&settings.actix.ssl)?;
+        assert_eq!(ssl.pkcs12, listener.pkcs12);
+        assert_eq!(ssl.private_key_passphrase, listener.private_key_passphrase);
+        listener.certificate = Some("listener.pem".into());
+        match listener.ssl(&settings.actix.ssl) {
+            Err(AtError::InvalidValue { .. }) => Ok(()),
+            other => panic!("expected AtError::InvalidValue, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn override_field__listeners() -> AtResult<()> {
+        let mut settings = Settings::from_default_template()?;
+        assert!(settings.actix.listeners.is_empty());
+        Settings::override_field(
+            &mut settings.actix.listeners,
+            r#"[{ address = ["0.0.0.0", 8443], tls = true }]"#
+        )?;
+        assert_eq!(settings.actix.listeners, vec![Listener {
+            tls: true,
+            ..Listener::new(Address { host: Host::parse("0.0.0.0")?, port: 8443 }.into())
+        }]);
+        Ok(())
+    }
+
+    #[cfg(not(feature = "openssl"))]
+    #[test]
+    fn try_apply_settings__tls_backend_unavailable() -> AtResult<()> {
+        let mut settings = Settings::from_default_template()?;
+        settings.actix.ssl.enabled = true;
+        settings.actix.ssl.backend = TlsBackend::Openssl;
+        match HttpServer::new(|| { App::new() }).try_apply_settings(&settings) {
+            Err(AtError::TlsBackendUnavailable(TlsBackend::Openssl)) => Ok(()),
+            Err(err) => panic!("expected AtError::TlsBackendUnavailable, got {:?}", err),
+            Ok(_) => panic!("expected AtError::TlsBackendUnavailable"),
+        }
+    }
+
     #[test]
     fn override_field__hosts() -> AtResult<()> {
         let mut settings = Settings::from_default_template()?;
         assert_eq!(settings.actix.hosts, vec![
-            Address { host: "0.0.0.0".into(),   port: 9000 },
+            Endpoint::Address(Address { host: Host::parse("0.0.0.0")?,   port: 9000 }),
         ]);
         Settings::override_field(&mut settings.actix.hosts, r#"[
             ["0.0.0.0",   1234],
             ["localhost", 2345]
         ]"#)?;
         assert_eq!(settings.actix.hosts, vec![
-            Address { host: "0.0.0.0".into(),   port: 1234 },
-            Address { host: "localhost".into(), port: 2345 },
+            Endpoint::Address(Address { host: Host::parse("0.0.0.0")?,   port: 1234 }),
+            Endpoint::Address(Address { host: Host::parse("localhost")?, port: 2345 }),
         ]);
         Ok(())
     }
 
     #[test]
-    fn override_field_with_env_var__hosts() -> AtResult<()> {
-        let mut settings = Settings::from_default_template()?;
-        assert_eq!(settings.actix.hosts, vec![
-            Address { host: "0.0.0.0".into(),   port: 9000 },
-        ]);
-        std::env::set_var("OVERRIDE__HOSTS", r#"[
-            ["0.0.0.0",   1234],
-            ["localhost", 2345]
-        ]"#);
-        Settings::override_field_with_env_var(
-            &mut settings.actix.hosts, "OVERRIDE__HOSTS"
-        )?;
-        assert_eq!(settings.actix.hosts, vec![
-            Address { host: "0.0.0.0".into(),   port: 1234 },
-            Address { host: "localhost".into(), port: 2345 },
-        ]);
+    fn override_field_with_env_var__hosts() -> AtResult<()> {
+        let mut settings = Settings::from_default_template()?;
+        assert_eq!(settings.actix.hosts, vec![
+            Endpoint::Address(Address { host: Host::parse("0.0.0.0")?,   port: 9000 }),
+        ]);
+        std::env::set_var("OVERRIDE__HOSTS", r#"[
+            ["0.0.0.0",   1234],
+            ["localhost", 2345]
+        ]"#);
+        Settings::override_field_with_env_var(
+            &mut settings.actix.hosts, "OVERRIDE__HOSTS"
+        )?;
+        assert_eq!(settings.actix.hosts, vec![
+            Endpoint::Address(Address { host: Host::parse("0.0.0.0")?,   port: 1234 }),
+            Endpoint::Address(Address { host: Host::parse("localhost")?, port: 2345 }),
+        ]);
+        Ok(())
+    }
+
+    /// The default template, with `hosts` and `port-from-env = "{var_name}"`.
+    fn port_from_env_template(hosts: &str, var_name: &str) -> String {
+        let template = Settings::DEFAULT_TOML_TEMPLATE.replacen(
+            "# port-from-env = \"PORT\"",
+            &format!("port-from-env = {:?}", var_name),
+            1,
+        );
+        let start = template.find("hosts = [").unwrap();
+        let end = start + template[start..].find("\n]\n").unwrap() + 3;
+        format!("{}hosts = {}\n{}", &template[..start], hosts, &template[end..])
+    }
+
+    #[test]
+    fn apply_port_from_env() -> AtResult<()> {
+        std::env::set_var("PORT_FROM_ENV__HOSTS", "8080");
+        let template = port_from_env_template(r#"[
+            ["0.0.0.0", 9000],
+            ["0.0.0.0", 9001],
+            "https://localhost:8443",
+            "unix:///run/app.sock",
+        ]"#, "PORT_FROM_ENV__HOSTS");
+        let mut settings = Settings::from_template(&template)?;
+        // Parsing alone leaves the `hosts` alone:
+        assert_eq!(settings.actix.hosts.len(), 4);
+        settings.actix.apply_port_from_env()?;
+        assert_eq!(settings.actix.hosts, vec![
+            Endpoint::Address(Address { host: Host::parse("0.0.0.0")?, port: 8080 }),
+            Endpoint::Https(Address { host: Host::parse("localhost")?, port: 8080 }),
+            Endpoint::Unix("/run/app.sock".into()),
+        ]);
+        Ok(())
+    }
+
+    #[test]
+    fn apply_port_from_env__unset() -> AtResult<()> {
+        std::env::remove_var("PORT_FROM_ENV__UNSET");
+        let template = port_from_env_template(r#"[["0.0.0.0", 9000]]"#, "PORT_FROM_ENV__UNSET");
+        let mut settings = Settings::from_template(&template)?;
+        settings.actix.apply_port_from_env()?;
+        assert_eq!(settings.actix.port_from_env.as_deref(), Some("PORT_FROM_ENV__UNSET"));
+        assert_eq!(settings.actix.hosts, vec![
+            Endpoint::Address(Address { host: Host::parse("0.0.0.0")?, port: 9000 }),
+        ]);
+        Ok(())
+    }
+
+    #[test]
+    fn apply_port_from_env__no_tcp_hosts() -> AtResult<()> {
+        std::env::set_var("PORT_FROM_ENV__NO_TCP_HOSTS", " 5000 ");
+        let template = port_from_env_template(r#"["unix:///run/app.sock"]"#,
+                                              "PORT_FROM_ENV__NO_TCP_HOSTS");
+        let mut settings = Settings::from_template(&template)?;
+        settings.actix.apply_port_from_env()?;
+        assert_eq!(settings.actix.hosts, vec![
+            Endpoint::Unix("/run/app.sock".into()),
+            Endpoint::Address(Address { host: Host::parse("0.0.0.0")?, port: 5000 }),
+        ]);
+        Ok(())
+    }
+
+    #[test]
+    fn apply_port_from_env__invalid() -> AtResult<()> {
+        for (i, value) in ["65536", "-1", "http", ""].iter().enumerate() {
+            let var_name = format!("PORT_FROM_ENV__INVALID_{}", i);
+            std::env::set_var(&var_name, value);
+            let template = port_from_env_template(r#"[["0.0.0.0", 9000]]"#, &var_name);
+            let mut settings = Settings::from_template(&template)?;
+            match settings.actix.apply_port_from_env() {
+                Err(AtError::InvalidEnvVar { name, value: got, .. }) => {
+                    assert_eq!(name, var_name);
+                    assert_eq!(&got, value);
+                },
+                other => panic!("Expected AtError::InvalidEnvVar for {:?}, got {:?}",
+                                value, other),
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn parse__address() -> AtResult<()> {
+        let ipv6 = Address { host: Host::Ip("::1".parse().unwrap()), port: 80 };
+        let localhost = Address { host: Host::Name("localhost".into()), port: 9000 };
+        let any = Address { host: Host::Ip([0, 0, 0, 0].into()), port: 9000 };
+        assert_eq!(Address::parse(r#"["::1", 80]"#)?, ipv6);
+        assert_eq!(Address::parse(r#"[ "::1" ,80 ]"#)?, ipv6);
+        assert_eq!(Address::parse(r#""[::1]:80""#)?, ipv6);
+        assert_eq!(Address::parse("[::1]:80")?, ipv6);
+        assert_eq!(Address::parse("localhost:9000")?, localhost);
+        assert_eq!(Address::parse(r#"["localhost", 9000]"#)?, localhost);
+        assert_eq!(Address::parse(" 0.0.0.0:9000\n")?, any);
+        assert_eq!(Address::parse("'0.0.0.0:9000'")?, any);
+        assert_eq!(ipv6.to_string(), "[::1]:80");
+        assert_eq!(localhost.to_string(), "localhost:9000");
+        Ok(())
+    }
+
+    #[test]
+    fn parse__addresses() -> AtResult<()> {
+        assert_eq!(Vec::<Address>::parse(r#"[
+            ["0.0.0.0", 1234],  # IPv4
+            "[::]:1234",        # IPv6
+            "localhost:2345",
+        ]"#)?, vec![
+            Address { host: Host::Ip([0, 0, 0, 0].into()), port: 1234 },
+            Address { host: Host::Ip("::".parse().unwrap()), port: 1234 },
+            Address { host: Host::Name("localhost".into()), port: 2345 },
+        ]);
+        assert_eq!(Vec::<Address>::parse("[]")?, vec![]);
+        Ok(())
+    }
+
+    #[test]
+    fn parse__address_error() {
+        for &(input, expected_column) in &[
+            (r#"["::1, 80]"#,            11),  // unterminated string
+            (r#"["a,b", 80]"#,            3),  // not a host name
+            (r#"["a\"b", 80]"#,           4),  // escaped quote
+            (r#"["localhost", 65536]"#,  15),  // port out of range
+            (r#"["localhost", 80"#,      17),  // missing `]`
+            ("::1:80",                    1),  // IPv6 without brackets
+            ("[::1:80",                   8),  // missing `]`
+            ("[::g]:80",                  2),  // not an IPv6 address
+            ("localhost",                10),  // missing port
+            ("localhost:",               11),  // empty port
+            ("localhost:80 x",           11),  // trailing garbage in the port
+        ] {
+            match Address::parse(input) {
+                Err(AtError::ParseAddressError { column, .. }) =>
+                    assert_eq!(column, expected_column, "column for {:?}", input),
+                other => panic!("expected AtError::ParseAddressError for {:?}, got {:?}",
+                                input, other),
+            }
+        }
+        match Vec::<Address>::parse(r#"[["0.0.0.0", 1234] ["::", 1234]]"#) {
+            Err(AtError::ParseAddressError { column: 20, .. }) => {},
+            other => panic!("expected AtError::ParseAddressError, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn parse__endpoint() -> AtResult<()> {
+        let address = |host: &str, port| Address { host: Host::parse(host).unwrap(), port };
+        assert_eq!(Endpoint::parse("http://0.0.0.0:8080")?,
+                   Endpoint::Http(address("0.0.0.0", 8080)));
+        assert_eq!(Endpoint::parse("\"https://[::1]:8443/\"")?,
+                   Endpoint::Https(address("::1", 8443)));
+        assert_eq!(Endpoint::parse("https://localhost")?,
+                   Endpoint::Https(address("localhost", 443)));
+        assert_eq!(Endpoint::parse("http://[::]")?, Endpoint::Http(address("::", 80)));
+        assert_eq!(Endpoint::parse("unix:///run/app.sock")?,
+                   Endpoint::Unix("/run/app.sock".into()));
+        assert_eq!(Endpoint::parse(r#"["0.0.0.0", 9000]"#)?,
+                   Endpoint::Address(address("0.0.0.0", 9000)));
+        for endpoint in &["localhost:9000", "http://[::1]:80", "unix:///run/app.sock"] {
+            assert_eq!(Endpoint::parse(endpoint)?.to_string(), *endpoint);
+        }
+        for &(input, expected_column) in &[
+            ("ftp://localhost:21",   1),
+            ("unix://",              8),
+            ("http://::1",           8),
+            ("https://localhost/x", 18),
+        ] {
+            match Endpoint::parse(input) {
+                Err(AtError::ParseAddressError { column, .. }) =>
+                    assert_eq!(column, expected_column, "column for {:?}", input),
+                other => panic!("expected AtError::ParseAddressError for {:?}, got {:?}",
+                                input, other),
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn override_field_with_env_var__hosts__urls() -> AtResult<()> {
+        let mut settings = Settings::from_default_template()?;
+        std::env::set_var("OVERRIDE__HOSTS__URLS", r#"[
+            "http://0.0.0.0:8080",
+            "https://0.0.0.0:8443",
+            "unix:///run/app.sock",
+        ]"#);
+        Settings::override_field_with_env_var(
+            &mut settings.actix.hosts, "OVERRIDE__HOSTS__URLS"
+        )?;
+        let any = |port| Address { host: Host::Ip([0, 0, 0, 0].into()), port };
+        assert_eq!(settings.actix.hosts, vec![
+            Endpoint::Http(any(8080)),
+            Endpoint::Https(any(8443)),
+            Endpoint::Unix("/run/app.sock".into()),
+        ]);
+        Ok(())
+    }
+
+    #[test]
+    fn deserialize__address() -> AtResult<()> {
+        let mut settings = Settings::from_default_template()?;
+        Settings::override_field(&mut settings.actix.listeners, r#"[
+            { address = "[::1]:8443" },
+            { address = ["::1", 8080] },
+        ]"#)?;
+        let addresses: Vec<_> = settings.actix.listeners.iter()
+            .map(|listener| listener.address.to_string())
+            .collect();
+        assert_eq!(addresses, ["[::1]:8443", "[::1]:8080"]);
+        match Settings::override_field(&mut settings.actix.listeners, r#"[
+            { address = ["a,b", 8080] },
+        ]"#) {
+            Err(AtError::TomlError(_)) => Ok(()),
+            other => panic!("expected AtError::TomlError, got {:?}", other),
+        }
+    }
+
+    /// Write the default template to `Server.toml` in `dir`, and the
+    /// `(name, contents)` pairs to `Server.{name}.toml`.
+    fn write_layers(dir: &Path, layers: &[(&str, &str)]) -> AtResult<PathBuf> {
+        let path = dir.join("Server.toml");
+        Settings::write_toml_file(&path)?;
+        for (name, contents) in layers {
+            std::fs::write(dir.join(format!("Server.{}.toml", name)), contents)?;
+        }
+        Ok(path)
+    }
+
+    #[test]
+    fn parse_layered_toml() -> AtResult<()> {
+        let dir = tempfile::tempdir()?;
+        let path = write_layers(dir.path(), &[
+            ("development", "[actix]\nnum-workers = \"4\"\nhosts = [[\"127.0.0.1\", 8080]]\n\
+                             [actix.ssl]\nmin-version = \"1.3\"\n"),
+            ("production", "[actix]\nnum-workers = \"16\"\n"),
+            ("local", "[actix]\nenable-log = false\n"),
+        ])?;
+        let settings = Settings::parse_layered_toml(&path, &Layers::default())?;
+        let base = Settings::parse_toml(&path)?;
+        assert_eq!(settings.actix.mode, Mode::Development);
+        assert_eq!(settings.actix.num_workers, NumWorkers::Manual(4));
+        assert_eq!(settings.actix.hosts, vec![
+            Endpoint::Address(Address { host: Host::parse("127.0.0.1")?, port: 8080 }),
+        ]);
+        assert_eq!(settings.actix.ssl.min_version, Some(TlsVersion::Tls1_3));
+        assert_eq!(settings.actix.ssl.enabled, base.actix.ssl.enabled);
+        assert_eq!(settings.actix.ssl.certificate, base.actix.ssl.certificate);
+        assert!(!settings.actix.enable_log);
+        assert_eq!(settings.actix.enable_compression, base.actix.enable_compression);
+        Ok(())
+    }
+
+    #[test]
+    fn parse_layered_toml__mode_env_var() -> AtResult<()> {
+        let dir = tempfile::tempdir()?;
+        let path = write_layers(dir.path(), &[
+            ("development", "[actix]\nnum-workers = \"4\"\n"),
+            ("production", "[actix]\nnum-workers = \"16\"\n"),
+        ])?;
+        let layers = Layers {
+            mode_env_var: Some("LAYERS__MODE".into()),
+            ..Layers::default()
+        };
+        std::env::set_var("LAYERS__MODE", "production");
+        let settings = Settings::parse_layered_toml(&path, &layers)?;
+        assert_eq!(settings.actix.mode, Mode::Production);
+        assert_eq!(settings.actix.num_workers, NumWorkers::Manual(16));
+        std::env::set_var("LAYERS__MODE", "staging");
+        match Settings::parse_layered_toml(&path, &layers) {
+            Err(AtError::InvalidEnvVar { name, value, .. }) => {
+                assert_eq!((name.as_str(), value.as_str()), ("LAYERS__MODE", "staging"));
+            },
+            other => panic!("Expected AtError::InvalidEnvVar, got {:?}", other),
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn parse_layered_toml__merge_options() -> AtResult<()> {
+        let dir = tempfile::tempdir()?;
+        let path = write_layers(dir.path(), &[
+            ("local", "[actix]\nhosts = [[\"127.0.0.1\", 8080]]\n\
+                       [actix.ssl]\nenabled = true\ncertificate = \"local.pem\"\n\
+                       private-key = \"local.key\"\n"),
+        ])?;
+        let base = Settings::parse_toml(&path)?;
+        let layers = Layers { arrays: ArrayMerge::Append, ..Layers::default() };
+        let settings = Settings::parse_layered_toml(&path, &layers)?;
+        let mut hosts = base.actix.hosts.clone();
+        hosts.push(Endpoint::Address(Address { host: Host::parse("127.0.0.1")?, port: 8080 }));
+        assert_eq!(settings.actix.hosts, hosts);
+        assert_eq!(settings.actix.ssl.certificate, PathBuf::from("local.pem"));
+        assert_eq!(settings.actix.ssl.backend, base.actix.ssl.backend);
+        // With replaced tables, [actix.ssl] lacks the `enabled` key of the base:
+        std::fs::write(dir.path().join("Server.local.toml"),
+                       "[actix.ssl]\ncertificate = \"local.pem\"\n")?;
+        let settings = Settings::parse_layered_toml(&path, &Layers::default())?;
+        assert_eq!(settings.actix.ssl.certificate, PathBuf::from("local.pem"));
+        let layers = Layers { tables: TableMerge::Replace, ..Layers::default() };
+        match Settings::parse_layered_toml(&path, &layers) {
+            Err(AtError::TomlError(_)) => {},
+            other => panic!("Expected AtError::TomlError, got {:?}", other),
+        }
         Ok(())
     }
 
@@ -563,6 +2268,175 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn override_field__ssl__private_key_passphrase() -> AtResult<()> {
+        let mut settings = Settings::from_default_template()?;
+        assert_eq!(settings.actix.ssl.private_key_passphrase, None);
+        Settings::override_field(
+            &mut settings.actix.ssl.private_key_passphrase, r#"{ env = "TLS_PASSPHRASE" }"#
+        )?;
+        assert_eq!(
+            settings.actix.ssl.private_key_passphrase,
+            Some(Secret::Env("TLS_PASSPHRASE".into()))
+        );
+        Settings::override_field(
+            &mut settings.actix.ssl.private_key_passphrase, r#"{ file = "/run/secrets/pass" }"#
+        )?;
+        assert_eq!(
+            settings.actix.ssl.private_key_passphrase,
+            Some(Secret::File("/run/secrets/pass".into()))
+        );
+        Settings::override_field(&mut settings.actix.ssl.private_key_passphrase, "")?;
+        assert_eq!(settings.actix.ssl.private_key_passphrase, None);
+        Ok(())
+    }
+
+    #[test]
+    fn override_field_with_env_var__ssl__pkcs12() -> AtResult<()> {
+        let mut settings = Settings::from_default_template()?;
+        assert_eq!(settings.actix.ssl.pkcs12, None);
+        std::env::set_var("OVERRIDE__SSL_PKCS12", "/etc/tls/bundle.p12");
+        Settings::override_field_with_env_var(
+            &mut settings.actix.ssl.pkcs12, "OVERRIDE__SSL_PKCS12"
+        )?;
+        assert_eq!(settings.actix.ssl.pkcs12, Some(PathBuf::from("/etc/tls/bundle.p12")));
+        Ok(())
+    }
+
+    #[test]
+    fn secret__read() -> AtResult<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("passphrase");
+        std::fs::write(&path, "hunter2\r\n")?;
+        assert_eq!(Secret::File(path.clone()).read()?, "hunter2");
+        std::env::set_var("SECRET__READ", "s3cr3t");
+        assert_eq!(Secret::Env("SECRET__READ".into()).read()?, "s3cr3t");
+        std::fs::remove_file(&path)?;
+        match Secret::File(path.clone()).read() {
+            Err(AtError::UnreadableFile { path: unreadable, .. }) => assert_eq!(unreadable, path),
+            other => panic!("expected AtError::UnreadableFile, got {:?}", other),
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn override_field_with_env_var__ssl__auto_generate() -> AtResult<()> {
+        let mut settings = Settings::from_default_template()?;
+        assert!(!settings.actix.ssl.auto_generate);
+        std::env::set_var("OVERRIDE__SSL_AUTO_GENERATE", "true");
+        Settings::override_field_with_env_var(
+            &mut settings.actix.ssl.auto_generate, "OVERRIDE__SSL_AUTO_GENERATE"
+        )?;
+        assert!(settings.actix.ssl.auto_generate);
+        Ok(())
+    }
+
+    #[test]
+    fn override_field__ssl__backend() -> AtResult<()> {
+        let mut settings = Settings::from_default_template()?;
+        assert_eq!(settings.actix.ssl.backend, TlsBackend::Rustls);
+        Settings::override_field(&mut settings.actix.ssl.backend, "openssl")?;
+        assert_eq!(settings.actix.ssl.backend, TlsBackend::Openssl);
+        Ok(())
+    }
+
+    #[test]
+    fn override_field_with_env_var__ssl__backend() -> AtResult<()> {
+        let mut settings = Settings::from_default_template()?;
+        assert_eq!(settings.actix.ssl.backend, TlsBackend::Rustls);
+        std::env::set_var("OVERRIDE__SSL_BACKEND", "openssl");
+        Settings::override_field_with_env_var(
+            &mut settings.actix.ssl.backend, "OVERRIDE__SSL_BACKEND"
+        )?;
+        assert_eq!(settings.actix.ssl.backend, TlsBackend::Openssl);
+        Ok(())
+    }
+
+    #[test]
+    fn override_field__ssl__reload_interval() -> AtResult<()> {
+        let mut settings = Settings::from_default_template()?;
+        assert_eq!(settings.actix.ssl.reload_interval, ReloadInterval::Disabled);
+        Settings::override_field(&mut settings.actix.ssl.reload_interval, "42 seconds")?;
+        assert_eq!(settings.actix.ssl.reload_interval, ReloadInterval::Seconds(42));
+        assert!(Settings::override_field(
+            &mut settings.actix.ssl.reload_interval, "0 seconds"
+        ).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn override_field_with_env_var__ssl__reload_interval() -> AtResult<()> {
+        let mut settings = Settings::from_default_template()?;
+        assert_eq!(settings.actix.ssl.reload_interval, ReloadInterval::Disabled);
+        std::env::set_var("OVERRIDE__SSL_RELOAD_INTERVAL", "42 milliseconds");
+        Settings::override_field_with_env_var(
+            &mut settings.actix.ssl.reload_interval, "OVERRIDE__SSL_RELOAD_INTERVAL"
+        )?;
+        assert_eq!(settings.actix.ssl.reload_interval, ReloadInterval::Milliseconds(42));
+        Ok(())
+    }
+
+    #[test]
+    fn override_field__ssl__min_version() -> AtResult<()> {
+        let mut settings = Settings::from_default_template()?;
+        assert_eq!(settings.actix.ssl.min_version, None);
+        Settings::override_field(&mut settings.actix.ssl.min_version, "1.3")?;
+        assert_eq!(settings.actix.ssl.min_version, Some(TlsVersion::Tls1_3));
+        Settings::override_field(&mut settings.actix.ssl.min_version, "")?;
+        assert_eq!(settings.actix.ssl.min_version, None);
+        Ok(())
+    }
+
+    #[test]
+    fn override_field_with_env_var__ssl__max_version() -> AtResult<()> {
+        let mut settings = Settings::from_default_template()?;
+        assert_eq!(settings.actix.ssl.max_version, None);
+        std::env::set_var("OVERRIDE__SSL_MAX_VERSION", "1.2");
+        Settings::override_field_with_env_var(
+            &mut settings.actix.ssl.max_version, "OVERRIDE__SSL_MAX_VERSION"
+        )?;
+        assert_eq!(settings.actix.ssl.max_version, Some(TlsVersion::Tls1_2));
+        Ok(())
+    }
+
+    #[test]
+    fn override_field__ssl__cipher_suites() -> AtResult<()> {
+        let mut settings = Settings::from_default_template()?;
+        assert!(settings.actix.ssl.cipher_suites.is_empty());
+        Settings::override_field(
+            &mut settings.actix.ssl.cipher_suites,
+            r#"["TLS_AES_256_GCM_SHA384", "TLS_ECDHE_RSA_WITH_AES_128_GCM_SHA256"]"#
+        )?;
+        assert_eq!(settings.actix.ssl.cipher_suites, vec![
+            CipherSuite::TlsAes256GcmSha384,
+            CipherSuite::TlsEcdheRsaWithAes128GcmSha256,
+        ]);
+        match Settings::override_field(
+            &mut settings.actix.ssl.cipher_suites, r#"["TLS_RSA_WITH_RC4_128_MD5"]"#
+        ) {
+            Err(AtError::InvalidValue { expected, got, .. }) => {
+                assert_eq!(got, "TLS_RSA_WITH_RC4_128_MD5");
+                for suite in &CipherSuite::ALL {
+                    assert!(expected.contains(suite.name()), "{}", expected);
+                }
+            },
+            other => panic!("expected AtError::InvalidValue, got {:?}", other),
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn override_field_with_env_var__ssl__alpn() -> AtResult<()> {
+        let mut settings = Settings::from_default_template()?;
+        assert!(settings.actix.ssl.alpn.is_empty());
+        std::env::set_var("OVERRIDE__SSL_ALPN", r#"["h2", "http/1.1"]"#);
+        Settings::override_field_with_env_var(
+            &mut settings.actix.ssl.alpn, "OVERRIDE__SSL_ALPN"
+        )?;
+        assert_eq!(settings.actix.ssl.alpn, vec!["h2", "http/1.1"]);
+        Ok(())
+    }
+
     #[test]
     fn override_field__ssl__certificate() -> AtResult<()> {
         let mut settings = Settings::from_default_template()?;
@@ -623,6 +2497,90 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn override_field__ssl__client_auth__mode() -> AtResult<()> {
+        let mut settings = Settings::from_default_template()?;
+        assert_eq!(settings.actix.ssl.client_auth.mode, ClientAuthMode::None);
+        Settings::override_field(&mut settings.actix.ssl.client_auth.mode, "required")?;
+        assert_eq!(settings.actix.ssl.client_auth.mode, ClientAuthMode::Required);
+        Ok(())
+    }
+
+    #[test]
+    fn override_field_with_env_var__ssl__client_auth__mode() -> AtResult<()> {
+        let mut settings = Settings::from_default_template()?;
+        assert_eq!(settings.actix.ssl.client_auth.mode, ClientAuthMode::None);
+        std::env::set_var("OVERRIDE__SSL_CLIENT_AUTH_MODE", "optional");
+        Settings::override_field_with_env_var(
+            &mut settings.actix.ssl.client_auth.mode, "OVERRIDE__SSL_CLIENT_AUTH_MODE"
+        )?;
+        assert_eq!(settings.actix.ssl.client_auth.mode, ClientAuthMode::Optional);
+        Ok(())
+    }
+
+    #[test]
+    fn override_field__ssl__client_auth__ca_certificates() -> AtResult<()> {
+        let mut settings = Settings::from_default_template()?;
+        assert!(settings.actix.ssl.client_auth.ca_certificates.is_empty());
+        Settings::override_field(
+            &mut settings.actix.ssl.client_auth.ca_certificates,
+            r#"["/path/to/ca1.pem", "/path/to/ca2.pem"]"#
+        )?;
+        assert_eq!(settings.actix.ssl.client_auth.ca_certificates, vec![
+            Path::new("/path/to/ca1.pem"),
+            Path::new("/path/to/ca2.pem"),
+        ]);
+        Ok(())
+    }
+
+    #[test]
+    fn ssl__certificates() -> AtResult<()> {
+        let settings = Settings::from_template(&(
+            Settings::DEFAULT_TOML_TEMPLATE.to_string()
+                + "\n[[actix.ssl.certificates]]"
+                + "\nserver-names = [\"example.com\", \"*.example.com\"]"
+                + "\ncertificate = \"path/to/example.com/cert.pem\""
+                + "\nprivate-key = \"path/to/example.com/key.pem\""
+                + "\n[[actix.ssl.certificates]]"
+                + "\nserver-names = [\"example.org\"]"
+                + "\npkcs12 = \"path/to/example.org/bundle.p12\""
+                + "\nprivate-key-passphrase = { env = \"EXAMPLE_ORG_PASSPHRASE\" }"
+        ))?;
+        assert_eq!(settings.actix.ssl.certificates, vec![SniCertificate {
+            server_names: vec!["example.com".into(), "*.example.com".into()],
+            certificate: "path/to/example.com/cert.pem".into(),
+            private_key: "path/to/example.com/key.pem".into(),
+            private_key_passphrase: None,
+            pkcs12: None,
+        }, SniCertificate {
+            server_names: vec!["example.org".into()],
+            certificate: PathBuf::new(),
+            private_key: PathBuf::new(),
+            private_key_passphrase: Some(Secret::Env("EXAMPLE_ORG_PASSPHRASE".into())),
+            pkcs12: Some("path/to/example.org/bundle.p12".into()),
+        }]);
+        settings.actix.ssl.validate()
+    }
+
+    #[test]
+    fn ssl__certificates__duplicate_server_name() -> AtResult<()> {
+        let mut settings = Settings::from_default_template()?;
+        for name in &["example.com", "EXAMPLE.com"] {
+            settings.actix.ssl.certificates.push(SniCertificate {
+                server_names: vec![name.to_string()],
+                certificate: "path/to/cert.pem".into(),
+                private_key: "path/to/key.pem".into(),
+                private_key_passphrase: None,
+                pkcs12: None,
+            });
+        }
+        match settings.actix.ssl.validate() {
+            Err(AtError::DuplicateServerName(name)) => assert_eq!(name, "EXAMPLE.com"),
+            other => panic!("expected AtError::DuplicateServerName, got {:?}", other),
+        }
+        Ok(())
+    }
+
     #[test]
     fn override_extended_field_with_custom_type() -> AtResult<()> {
         #[derive(Debug, Clone, Deserialize, PartialEq, Eq)]
@@ -665,4 +2623,381 @@ mod tests {
         Ok(())
     }
 
+
+    #[derive(Debug, Clone, Deserialize, serde_derive::Serialize, PartialEq, Eq)]
+    struct NestedAppSetting {
+        foo: String,
+        bar: bool,
+    }
+
+    #[derive(Debug, Clone, Deserialize, serde_derive::Serialize, PartialEq, Eq)]
+    struct OverridableAppSettings {
+        #[serde(rename = "max-items")]
+        max_items: u32,
+        #[serde(rename = "nested-field")]
+        nested_field: NestedAppSetting,
+    }
+
+    /// Settings whose `application` table holds `max-items = 10` and
+    /// `nested-field = { foo = "foo", bar = false }`.
+    fn overridable_settings() -> AtResult<BasicSettings<OverridableAppSettings>> {
+        BasicSettings::from_template(&(
+            Settings::DEFAULT_TOML_TEMPLATE.to_string()
+                // NOTE: Add these entries to the `[application]` table:
+                + "\nmax-items = 10"
+                + "\nnested-field = { foo = \"foo\", bar = false }"
+        ))
+    }
+
+    #[test]
+    fn apply_env_overrides__actix() -> AtResult<()> {
+        let mut settings = Settings::from_default_template()?;
+        std::env::set_var("ENV_OVERRIDES_ACTIX__ACTIX__NUM_WORKERS", "4");
+        std::env::set_var("ENV_OVERRIDES_ACTIX__ACTIX__SSL__ENABLED", "true");
+        std::env::set_var("ENV_OVERRIDES_ACTIX__ACTIX__SSL__CLIENT_AUTH__MODE", "required");
+        std::env::set_var("ENV_OVERRIDES_ACTIX__ACTIX__KEEP_ALIVE", "10 seconds");
+        std::env::set_var("ENV_OVERRIDES_ACTIX__ACTIX__NO_SUCH_FIELD", "ignored");
+        std::env::set_var("ENV_OVERRIDES_ACTIX__ACTX__MODE", "ignored");
+        settings.apply_env_overrides("ENV_OVERRIDES_ACTIX")?;
+        assert_eq!(settings.actix.num_workers, NumWorkers::Manual(4));
+        assert!(settings.actix.ssl.enabled);
+        assert_eq!(settings.actix.ssl.client_auth.mode, ClientAuthMode::Required);
+        assert_eq!(settings.actix.keep_alive, KeepAlive::Seconds(10));
+        assert_eq!(settings.actix.mode, Mode::Development);
+        Ok(())
+    }
+
+    #[test]
+    fn apply_env_overrides__invalid_value() -> AtResult<()> {
+        let mut settings = Settings::from_default_template()?;
+        std::env::set_var("ENV_OVERRIDES_INVALID__ACTIX__MODE", "staging");
+        match settings.apply_env_overrides("ENV_OVERRIDES_INVALID") {
+            Err(AtError::InvalidValue { .. }) => {},
+            other => panic!("Expected AtError::InvalidValue, got {:?}", other),
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn apply_env_overrides__port_from_env() -> AtResult<()> {
+        let mut settings = Settings::from_template(
+            &port_from_env_template(r#"[["0.0.0.0", 9000]]"#, "ENV_OVERRIDES_PORT_FROM_ENV_A")
+        )?;
+        std::env::set_var("ENV_OVERRIDES_PORT_FROM_ENV_A", "8080");
+        std::env::set_var("ENV_OVERRIDES_PORT_FROM_ENV_B", "8081");
+        // The `port-from-env` setting is applied after being overridden:
+        std::env::set_var("ENV_OVERRIDES_PORT_FROM_ENV__ACTIX__PORT_FROM_ENV",
+                          "ENV_OVERRIDES_PORT_FROM_ENV_B");
+        settings.apply_env_overrides("ENV_OVERRIDES_PORT_FROM_ENV")?;
+        assert_eq!(settings.actix.hosts, vec![
+            Endpoint::Address(Address { host: Host::parse("0.0.0.0")?, port: 8081 }),
+        ]);
+        Ok(())
+    }
+
+    #[test]
+    fn apply_env_overrides_strict() -> AtResult<()> {
+        let mut settings = Settings::from_default_template()?;
+        std::env::set_var("ENV_OVERRIDES_STRICT__ACTIX__NUM_WORKERS", "4");
+        std::env::set_var("ENV_OVERRIDES_STRICT__ACTIX__SSL__CLIENT_AUTH__MODE", "required");
+        settings.apply_env_overrides_strict("ENV_OVERRIDES_STRICT")?;
+        assert_eq!(settings.actix.num_workers, NumWorkers::Manual(4));
+        assert_eq!(settings.actix.ssl.client_auth.mode, ClientAuthMode::Required);
+        Ok(())
+    }
+
+    #[test]
+    fn apply_env_overrides_strict__unknown() -> AtResult<()> {
+        let mut settings = Settings::from_default_template()?;
+        std::env::set_var("ENV_OVERRIDES_UNKNOWN__ACTIX__BACKLOG", "10");
+        std::env::set_var("ENV_OVERRIDES_UNKNOWN__ACTIX__NUM_WORKER", "4");
+        std::env::set_var("ENV_OVERRIDES_UNKNOWN__ACTX__MODE", "production");
+        std::env::set_var("ENV_OVERRIDES_UNKNOWN__ACTIX__SSL__CLIENTAUTH__MODE", "required");
+        std::env::set_var("ENV_OVERRIDES_UNKNOWN__APPLICATION__FOO", "bar");
+        std::env::set_var("ENV_OVERRIDES_UNKNOWN__UNRELATED", "");
+        match settings.apply_env_overrides_strict("ENV_OVERRIDES_UNKNOWN") {
+            Err(AtError::UnknownEnvVars(vars)) => assert_eq!(vars, vec![
+                ("ENV_OVERRIDES_UNKNOWN__ACTIX__NUM_WORKER".to_string(),
+                 Some("ENV_OVERRIDES_UNKNOWN__ACTIX__NUM_WORKERS".to_string())),
+                ("ENV_OVERRIDES_UNKNOWN__ACTIX__SSL__CLIENTAUTH__MODE".to_string(),
+                 Some("ENV_OVERRIDES_UNKNOWN__ACTIX__SSL__CLIENT_AUTH__MODE".to_string())),
+                ("ENV_OVERRIDES_UNKNOWN__ACTX__MODE".to_string(),
+                 Some("ENV_OVERRIDES_UNKNOWN__ACTIX__MODE".to_string())),
+                ("ENV_OVERRIDES_UNKNOWN__APPLICATION__FOO".to_string(), None),
+                ("ENV_OVERRIDES_UNKNOWN__UNRELATED".to_string(), None),
+            ]),
+            other => panic!("Expected AtError::UnknownEnvVars, got {:?}", other),
+        }
+        // Nothing is overridden:
+        assert_eq!(settings.actix.backlog, Backlog::Default);
+        Ok(())
+    }
+
+    #[test]
+    fn set__actix() -> AtResult<()> {
+        let mut settings = Settings::from_default_template()?;
+        settings.set("actix.keep-alive", "10 seconds")?;
+        settings.set("actix.ssl.enabled", "true")?;
+        settings.set("actix.ssl.client-auth.mode", "optional")?;
+        assert_eq!(settings.actix.keep_alive, KeepAlive::Seconds(10));
+        assert!(settings.actix.ssl.enabled);
+        assert_eq!(settings.actix.ssl.client_auth.mode, ClientAuthMode::Optional);
+        // An empty value unsets an optional setting:
+        settings.set("actix.ssl.min-version", "1.3")?;
+        settings.set("actix.ssl.min-version", "")?;
+        settings.set("actix.port-file", "")?;
+        assert_eq!(settings.actix.ssl.min_version, None);
+        assert_eq!(settings.actix.port_file, None);
+        for path in ["actix.keep-aliv", "actix.ssl", "actix", "application.foo", "foo.bar"] {
+            match settings.set(path, "true") {
+                Err(AtError::UnknownSetting(unknown)) => assert_eq!(unknown, path),
+                other => panic!("Expected AtError::UnknownSetting, got {:?}", other),
+            }
+        }
+        match settings.set("actix.keep-alive", "10") {
+            Err(AtError::InvalidValue { .. }) => {},
+            other => panic!("Expected AtError::InvalidValue, got {:?}", other),
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn set__port_from_env() -> AtResult<()> {
+        let mut settings = Settings::from_default_template()?;
+        settings.set("actix.hosts", r#"[["0.0.0.0", 9000]]"#)?;
+        std::env::set_var("SET__PORT_FROM_ENV", "8080");
+        settings.set("actix.port-from-env", "SET__PORT_FROM_ENV")?;
+        assert_eq!(settings.actix.hosts, vec![
+            Endpoint::Address(Address { host: Host::parse("0.0.0.0")?, port: 8080 }),
+        ]);
+        // A later `hosts` takes precedence:
+        settings.set("actix.hosts", r#"[["0.0.0.0", 9000]]"#)?;
+        assert_eq!(settings.actix.hosts, vec![
+            Endpoint::Address(Address { host: Host::parse("0.0.0.0")?, port: 9000 }),
+        ]);
+        Ok(())
+    }
+
+    #[test]
+    fn get__actix() -> AtResult<()> {
+        let mut settings = Settings::from_default_template()?;
+        settings.set("actix.keep-alive", "10 seconds")?;
+        settings.set("actix.hosts", r#"[["0.0.0.0", 9000], "unix:///run/app.sock"]"#)?;
+        let string = |s: &str| Some(toml::Value::String(s.to_string()));
+        assert_eq!(settings.get("actix.keep-alive")?, string("10 seconds"));
+        assert_eq!(settings.get("actix.ssl.enabled")?, Some(toml::Value::Boolean(false)));
+        assert_eq!(settings.get("actix.ssl.client-auth.mode")?, string("none"));
+        assert_eq!(settings.get("actix.hosts")?, Some(toml::Value::Array(vec![
+            toml::Value::String("0.0.0.0:9000".to_string()),
+            toml::Value::String("unix:///run/app.sock".to_string()),
+        ])));
+        assert_eq!(settings.get("actix.port-file")?, None);
+        assert!(settings.get("actix.ssl")?.is_some_and(|ssl| ssl.is_table()));
+        for path in ["actix.keep-aliv", "actix.ssl.nope", "application.foo", "foo"] {
+            match settings.get(path) {
+                Err(AtError::UnknownSetting(unknown)) => assert_eq!(unknown, path),
+                other => panic!("Expected AtError::UnknownSetting, got {:?}", other),
+            }
+        }
+        Ok(())
+    }
+
+    fn command_line(args: &[&str]) -> AtResult<CommandLine> {
+        CommandLine::parse(args.iter().map(|arg| arg.to_string()))
+    }
+
+    #[test]
+    fn command_line__parse() -> AtResult<()> {
+        let parsed = command_line(&[
+            "server", "--config", "a.toml", "-v", "--set", "actix.num-workers=8",
+            "--set=application.feature-x=true", "--port=80", "--config=/etc/app/Server.toml",
+            "--set", "actix.hosts=[\"0.0.0.0:80\"]", "--", "--set", "x",
+        ])?;
+        assert_eq!(parsed, CommandLine {
+            program: "server".into(),
+            config: Some("/etc/app/Server.toml".into()),
+            set: vec![
+                ("actix.num-workers".into(), "8".into()),
+                ("application.feature-x".into(), "true".into()),
+                ("actix.hosts".into(), "[\"0.0.0.0:80\"]".into()),
+            ],
+            args: vec!["-v".into(), "--port=80".into(), "--set".into(), "x".into()],
+        });
+        assert_eq!(command_line(&["server"])?.config_path("Server.toml"),
+                   PathBuf::from("Server.toml"));
+        for args in [&["server", "--set"][..], &["server", "--set", "x"], &["server", "--config"]] {
+            match command_line(args) {
+                Err(AtError::InvalidArgument(_)) => {},
+                other => panic!("Expected AtError::InvalidArgument, got {:?}", other),
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn apply_command_line() -> AtResult<()> {
+        #[derive(Debug, Clone, Deserialize, serde_derive::Serialize, PartialEq, Eq)]
+        struct AppSettings {
+            #[serde(rename = "feature-x")]
+            feature_x: bool,
+        }
+        type CustomSettings = BasicSettings<AppSettings>;
+        let mut settings = CustomSettings::from_template(&(
+            CustomSettings::DEFAULT_TOML_TEMPLATE.to_string() + "\nfeature-x = false"
+        ))?;
+        settings.apply_command_line(&command_line(&[
+            "server", "--set", "actix.num-workers=8", "--set", "application.feature-x=true",
+        ])?)?;
+        assert_eq!(settings.actix.num_workers, NumWorkers::Manual(8));
+        assert!(settings.application.feature_x);
+        let keys = settings.keys()?;
+        assert!(keys.contains(&"actix.ssl.client-auth.mode".to_string()));
+        assert_eq!(keys.last().map(String::as_str), Some("application.feature-x"));
+        let unknown = command_line(&["server", "--set", "actix.num-worker=8"])?;
+        let error = settings.apply_command_line(&unknown).unwrap_err();
+        let usage = unknown.usage(&error, &keys);
+        assert_eq!(settings.apply_command_line_or_usage(&unknown), Err(usage.clone()));
+        assert!(usage.starts_with("error: Unknown setting: actix.num-worker\n\n\
+                                   Usage: server [--config PATH] [--set KEY=VALUE]...\n"));
+        assert!(usage.contains("\n    actix.num-workers\n"));
+        assert!(usage.ends_with("\n    application.feature-x\n"));
+        Ok(())
+    }
+
+    #[test]
+    fn load() -> AtResult<()> {
+        let dir = tempfile::tempdir()?;
+        let path = write_layers(dir.path(), &[
+            ("local", "[actix]\nnum-workers = \"2\"\nbacklog = \"64\"\nenable-log = false\n\
+                       port-from-env = \"LOAD__PORT\"\n"),
+        ])?;
+        std::env::set_var("LOAD__PORT", "8080");
+        std::env::set_var("LOAD__ACTIX__NUM_WORKERS", "4");
+        std::env::set_var("LOAD__ACTIX__BACKLOG", "128");
+        let args = ["server", "--config", path.to_str().unwrap(), "--set", "actix.num-workers=8"];
+        let settings = Settings::load(
+            &command_line(&args)?, "NoSuchFile.toml", &Layers::default(), "LOAD"
+        )?;
+        assert!(!settings.actix.enable_log);
+        assert_eq!(settings.actix.backlog, Backlog::Manual(128));
+        assert_eq!(settings.actix.num_workers, NumWorkers::Manual(8));
+        assert!(settings.actix.hosts.iter().all(|endpoint| matches!(
+            endpoint, Endpoint::Address(address) if address.port == 8080
+        )));
+        // `--set` arguments take precedence over `port-from-env` too:
+        let args = ["server", "--config", path.to_str().unwrap(),
+                    "--set", r#"actix.hosts=[["127.0.0.1", 9000]]"#];
+        let settings = Settings::load(
+            &command_line(&args)?, "NoSuchFile.toml", &Layers::default(), "LOAD"
+        )?;
+        assert_eq!(settings.actix.hosts, vec![
+            Endpoint::Address(Address { host: Host::parse("127.0.0.1")?, port: 9000 }),
+        ]);
+        Ok(())
+    }
+
+    #[test]
+    fn set__application() -> AtResult<()> {
+        let mut settings = overridable_settings()?;
+        settings.set("application.max-items", "30")?;
+        settings.set("application.nested-field.foo", "a string")?;
+        assert_eq!(settings.application, OverridableAppSettings {
+            max_items: 30,
+            nested_field: NestedAppSetting {
+                foo: "a string".into(),
+                bar: false,
+            },
+        });
+        // The result must still deserialize into `OverridableAppSettings`:
+        match settings.set("application.max-items", "many") {
+            Err(AtError::TomlError(_)) => {},
+            other => panic!("Expected AtError::TomlError, got {:?}", other),
+        }
+        assert_eq!(settings.application.max_items, 30);
+        Ok(())
+    }
+
+    #[test]
+    fn get__application() -> AtResult<()> {
+        let settings = overridable_settings()?;
+        assert_eq!(settings.get("application.max-items")?, Some(toml::Value::Integer(10)));
+        assert_eq!(settings.get("application.nested-field.foo")?,
+                   Some(toml::Value::String("foo".into())));
+        assert!(settings.get("application.nested-field")?.is_some_and(|nested| nested.is_table()));
+        match settings.get("application.nested-field.baz") {
+            Err(AtError::UnknownSetting(unknown)) => assert_eq!(unknown, "application.nested-field.baz"),
+            other => panic!("Expected AtError::UnknownSetting, got {:?}", other),
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn actix_settings__serialize() -> AtResult<()> {
+        let mut settings = Settings::from_default_template()?;
+        settings.set("actix.hosts", r#"[["::1", 80], "https://localhost:8443"]"#)?;
+        settings.set("actix.listeners", r#"[{ unix = "/run/app.sock", mode = "0660", owner = ":www" }, { address = "127.0.0.1:9000", keepalive-idle = "60 seconds", reuse-port = true }, { activation = "systemd" }]"#)?;
+        settings.set("actix.num-workers", "4")?;
+        settings.set("actix.client-timeout", "500 milliseconds")?;
+        settings.set("actix.ssl.min-version", "1.3")?;
+        settings.set("actix.ssl.cipher-suites", r#"["TLS_AES_256_GCM_SHA384"]"#)?;
+        settings.set("actix.ssl.reload-interval", "30 seconds")?;
+        settings.set("actix.ssl.certificates", r#"[{ server-names = ["example.com"], pkcs12 = "example.p12" }]"#)?;
+        let toml = toml::Value::try_from(&settings.actix)?.to_string();
+        let actix: ActixSettings = toml::from_str(&toml)?;
+        assert_eq!(actix, settings.actix);
+        // Secrets serialize into tables, as they're written:
+        settings.set("actix.ssl.private-key-passphrase", r#"{ env = "PASSPHRASE" }"#)?;
+        assert_eq!(settings.get("actix.ssl.private-key-passphrase")?,
+                   Some(toml::toml! { env = "PASSPHRASE" }));
+        Ok(())
+    }
+
+    #[test]
+    fn apply_env_overrides__application() -> AtResult<()> {
+        let mut settings = overridable_settings()?;
+        std::env::set_var("ENV_OVERRIDES_APP__APPLICATION__MAX_ITEMS", "20");
+        std::env::set_var("ENV_OVERRIDES_APP__APPLICATION__NESTED_FIELD__FOO", "a string");
+        std::env::set_var("ENV_OVERRIDES_APP__APPLICATION__NESTED_FIELD__BAR", "true");
+        settings.apply_env_overrides("ENV_OVERRIDES_APP")?;
+        assert_eq!(settings.application, OverridableAppSettings {
+            max_items: 20,
+            nested_field: NestedAppSetting {
+                foo: "a string".into(),
+                bar: true,
+            },
+        });
+        // The result must still deserialize into `OverridableAppSettings`:
+        std::env::set_var("ENV_OVERRIDES_APP__APPLICATION__MAX_ITEMS", "many");
+        match settings.apply_env_overrides("ENV_OVERRIDES_APP") {
+            Err(AtError::TomlError(_)) => {},
+            other => panic!("Expected AtError::TomlError, got {:?}", other),
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn apply_env_overrides_strict__application() -> AtResult<()> {
+        let mut settings = overridable_settings()?;
+        std::env::set_var("ENV_OVERRIDES_STRICT_APP__APPLICATION__MAX_ITEMS", "20");
+        std::env::set_var("ENV_OVERRIDES_STRICT_APP__APPLICATION__NESTED_FIELD__BAR", "true");
+        settings.apply_env_overrides_strict("ENV_OVERRIDES_STRICT_APP")?;
+        assert_eq!(settings.application, OverridableAppSettings {
+            max_items: 20,
+            nested_field: NestedAppSetting {
+                foo: "foo".into(),
+                bar: true,
+            },
+        });
+        // Values that aren't in `OverridableAppSettings` are unknown:
+        std::env::set_var("ENV_OVERRIDES_STRICT_APP__APPLICATION__NESTED_FIELD__BAZ", "true");
+        match settings.apply_env_overrides_strict("ENV_OVERRIDES_STRICT_APP") {
+            Err(AtError::UnknownEnvVars(vars)) => assert_eq!(vars, vec![(
+                "ENV_OVERRIDES_STRICT_APP__APPLICATION__NESTED_FIELD__BAZ".to_string(),
+                Some("ENV_OVERRIDES_STRICT_APP__APPLICATION__NESTED_FIELD__BAR".to_string()),
+            )]),
+            other => panic!("Expected AtError::UnknownEnvVars, got {:?}", other),
+        }
+        Ok(())
+    }
+
 }
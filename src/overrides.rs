@@ -0,0 +1,160 @@
+//! Overriding settings with prefixed environment variables
+
+use crate::error::{AtError, AtResult};
+use std::env::{self, VarError};
+use toml::Value;
+
+
+/// The setting that an environment variable `{prefix}__…` overrides.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum Target {
+    /// A field of `ActixSettings`, by its dotted key e.g. `"ssl.enabled"`.
+    Actix(String),
+    /// A value in the `[application]` table, by the segments of its name
+    /// e.g. `["foo", "bar"]` for `{prefix}__APPLICATION__FOO__BAR`.
+    Application(Vec<String>),
+    /// Neither of the above e.g. `{prefix}__ACTX__MODE`.
+    Unknown,
+}
+
+/// An environment variable whose name starts with `{prefix}__`.
+#[derive(Debug, Clone)]
+pub(crate) struct EnvOverride {
+    pub(crate) name: String,
+    pub(crate) target: Target,
+    pub(crate) value: String,
+}
+
+/// The environment variables whose names start with `{prefix}__`, sorted by
+/// name.  Their names are split at each `__`, and the segments after the
+/// `ACTIX` segment are turned into a dotted `TOML` key, e.g.
+/// `APP__ACTIX__SSL__CLIENT_AUTH__MODE` into `"ssl.client-auth.mode"`.
+pub(crate) fn env_overrides(prefix: &str) -> AtResult<Vec<EnvOverride>> {
+    let prefix = format!("{}__", prefix);
+    let mut overrides = vec![];
+    for (name, value) in env::vars_os() {
+        let name = match name.into_string() {
+            Ok(name) if name.starts_with(&prefix) => name,
+            _ => continue,
+        };
+        let value = value.into_string()
+            .map_err(|value| AtError::EnvVarError(VarError::NotUnicode(value)))?;
+        let segments: Vec<String> = name[prefix.len()..].split("__")
+            .map(str::to_ascii_lowercase)
+            .collect();
+        let target = match segments.split_first() {
+            Some((first, rest)) if first == "actix" && !rest.is_empty() =>
+                Target::Actix(rest.join(".").replace('_', "-")),
+            Some((first, rest)) if first == "application" && !rest.is_empty() =>
+                Target::Application(rest.to_vec()),
+            _ => Target::Unknown,
+        };
+        overrides.push(EnvOverride { name, target, value });
+    }
+    overrides.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(overrides)
+}
+
+/// Parse `string` as a `TOML` value e.g. `8080`, `true` or `["a", "b"]`,
+/// or else take it as a string, so that strings needn't be quoted.
+pub(crate) fn toml_value(string: &str) -> Value {
+    crate::core::parse_toml_value(string)
+        .unwrap_or_else(|_| Value::String(string.to_string()))
+}
+
+/// Set the value at `path` in the `table`, creating tables as needed.  The
+/// segments of `path` match existing keys regardless of case, and of `-`
+/// versus `_`, since environment variable names can't express either.
+pub(crate) fn set_toml_path(table: &mut Value, path: &[String], value: Value) -> AtResult<()> {
+    let (last, parents) = path.split_last().expect("`path` isn't empty");
+    let mut table = table;
+    for segment in parents {
+        table = entry(table, segment)?;
+    }
+    *entry(table, last)? = value;
+    Ok(())
+}
+
+fn entry<'a>(table: &'a mut Value, segment: &str) -> AtResult<&'a mut Value> {
+    let table = match table {
+        Value::Table(table) => table,
+        _ => return Err(InvalidValue! {
+            expected: "a table to set a nested application setting in",
+            got: table,
+        }),
+    };
+    let key = table.keys()
+        .find(|key| same_key(key, segment))
+        .cloned()
+        .unwrap_or_else(|| segment.to_string());
+    Ok(table.entry(key).or_insert(Value::Table(Default::default())))
+}
+
+/// The value at `path` in `table`, matching keys like `set_toml_path()`
+/// does.
+pub(crate) fn get_toml_path<'a, S: AsRef<str>>(table: &'a Value, path: &[S]) -> Option<&'a Value> {
+    path.iter().try_fold(table, |table, segment| {
+        table.as_table()?.iter()
+            .find(|(key, _)| same_key(key, segment.as_ref()))
+            .map(|(_, value)| value)
+    })
+}
+
+/// The paths of the values in `table` that aren't tables themselves, e.g.
+/// `["foo", "bar"]` for `foo = { bar = 1 }`.
+pub(crate) fn toml_paths(table: &Value) -> Vec<Vec<String>> {
+    match table {
+        Value::Table(table) => table.iter()
+            .flat_map(|(key, value)| toml_paths(value).into_iter().map(move |mut path| {
+                path.insert(0, key.clone());
+                path
+            }))
+            .collect(),
+        _ => vec![vec![]],
+    }
+}
+
+/// The name of the environment variable that overrides the `TOML` key
+/// made up of `segments`, e.g. `APP__ACTIX__SSL__ENABLED` for `"APP"` and
+/// `["actix", "ssl", "enabled"]`.
+pub(crate) fn env_var_name<S: AsRef<str>>(prefix: &str, segments: &[S]) -> String {
+    let mut name = prefix.to_string();
+    for segment in segments {
+        name.push_str("__");
+        name.push_str(&segment.as_ref().replace('-', "_").to_ascii_uppercase());
+    }
+    name
+}
+
+/// The candidate closest to `name`, unless all of them are too different
+/// for `name` to be a plausible typo of them.
+pub(crate) fn suggestion<'a>(name: &str, candidates: &'a [String]) -> Option<&'a str> {
+    let name = name.to_ascii_uppercase();
+    let max_distance = (name.chars().count() / 6).clamp(1, 3);
+    candidates.iter()
+        .map(|candidate| (edit_distance(&name, candidate), candidate))
+        .filter(|&(distance, _)| distance <= max_distance)
+        .min_by_key(|&(distance, _)| distance)
+        .map(|(_, candidate)| candidate.as_str())
+}
+
+/// The Levenshtein distance between `a` and `b`.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, a) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, &b) in b.iter().enumerate() {
+            let substituted = diagonal + usize::from(a != b);
+            diagonal = row[j + 1];
+            row[j + 1] = substituted.min(row[j] + 1).min(diagonal + 1);
+        }
+    }
+    row[b.len()]
+}
+
+fn same_key(key: &str, segment: &str) -> bool {
+    let normalize = |key: &str| key.replace('-', "_").to_ascii_lowercase();
+    normalize(key) == normalize(segment)
+}
@@ -0,0 +1,163 @@
+//! Checking whether the listeners of an `ActixSettings` value can be bound
+
+use crate::actix::{ActixSettings, ListenAddress, Listener, SocketOptions};
+use crate::bound::BoundAddress;
+use crate::error::AtError;
+use std::fmt;
+use std::io;
+use std::net::{SocketAddr, TcpListener};
+
+
+/// The outcome of `BasicSettings::preflight()`: A check for each address of
+/// each listener, in the order of `ActixSettings::all_listeners()`.
+#[derive(Debug, Clone, Default)]
+pub struct PreflightReport(pub Vec<PreflightCheck>);
+
+impl PreflightReport {
+    /// Whether all listeners can be bound.  Skipped checks don't count as
+    /// failures.
+    pub fn is_ok(&self) -> bool {
+        self.0.iter().all(|check| check.status.is_ok())
+    }
+
+    /// The checks that failed.
+    pub fn failures(&self) -> impl Iterator<Item = &PreflightCheck> {
+        self.0.iter().filter(|check| !check.status.is_ok())
+    }
+}
+
+impl fmt::Display for PreflightReport {
+    /// One line per check e.g. `0.0.0.0:80: 0.0.0.0:80: permission denied`.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for check in &self.0 {
+            writeln!(f, "{}", check)?;
+        }
+        Ok(())
+    }
+}
+
+/// The check of a single address of a listener.  A listener whose host name
+/// resolves to several addresses has a check for each of them.
+#[derive(Debug, Clone)]
+pub struct PreflightCheck {
+    pub listener: Listener,
+    /// The address that was checked, if the listener got that far.
+    pub addr: Option<BoundAddress>,
+    pub status: PreflightStatus,
+}
+
+impl fmt::Display for PreflightCheck {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: ", self.listener.address)?;
+        match &self.addr {
+            Some(BoundAddress::Tcp(addr)) => write!(f, "{}: ", addr)?,
+            Some(BoundAddress::Unix(path)) => write!(f, "{}: ", path.display())?,
+            None => {},
+        }
+        write!(f, "{}", self.status)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum PreflightStatus {
+    /// The address can be bound.
+    Ok,
+    /// Another socket is bound to the address, or a Unix socket file is in
+    /// use or may not be removed.
+    AddressInUse,
+    /// The process may not bind the address, e.g. a port below 1024 without
+    /// the privilege to bind it, or a Unix socket in a read-only directory.
+    PermissionDenied,
+    /// The address doesn't belong to any local network interface.
+    NotLocal,
+    /// The host name of the listener doesn't resolve to any address that
+    /// its `resolve` policy selects.
+    Unresolved(ioe::IoError),
+    /// The options of the listener are invalid.
+    Invalid(AtError),
+    /// Binding failed for another reason.
+    Failed(ioe::IoError),
+    /// The listener uses sockets passed by a service manager, which can't be
+    /// checked beforehand.
+    Skipped,
+}
+
+impl PreflightStatus {
+    pub fn is_ok(&self) -> bool {
+        matches!(self, Self::Ok | Self::Skipped)
+    }
+
+    fn from_bind(result: io::Result<()>) -> Self {
+        match result {
+            Ok(()) => Self::Ok,
+            Err(err) => match err.kind() {
+                io::ErrorKind::AddrInUse => Self::AddressInUse,
+                io::ErrorKind::PermissionDenied => Self::PermissionDenied,
+                io::ErrorKind::AddrNotAvailable => Self::NotLocal,
+                _ => Self::Failed(err.into()),
+            },
+        }
+    }
+}
+
+impl fmt::Display for PreflightStatus {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Ok => write!(f, "ok"),
+            Self::AddressInUse => write!(f, "address in use"),
+            Self::PermissionDenied => write!(f, "permission denied"),
+            Self::NotLocal => write!(f, "not a local address"),
+            Self::Unresolved(error) => write!(f, "unresolved host: {}", error),
+            Self::Invalid(error) => write!(f, "invalid listener: {:?}", error),
+            Self::Failed(error) => write!(f, "failed: {}", error),
+            Self::Skipped => write!(f, "skipped (socket-activated)"),
+        }
+    }
+}
+
+/// Check each listener of `settings` by binding and immediately releasing
+/// its addresses.
+pub(crate) fn preflight(settings: &ActixSettings) -> PreflightReport {
+    let mut report = PreflightReport::default();
+    for listener in settings.all_listeners() {
+        let check = |addr, status| PreflightCheck { listener: listener.clone(), addr, status };
+        if let Err(err) = listener.validate() {
+            report.0.push(check(None, PreflightStatus::Invalid(err)));
+            continue;
+        }
+        match &listener.address {
+            ListenAddress::Tcp(address) => match address.resolve(listener.resolve) {
+                Err(err) => report.0.push(check(None, PreflightStatus::Unresolved(err.into()))),
+                Ok(addrs) => for addr in addrs {
+                    let status = PreflightStatus::from_bind(bind(addr, &listener.socket));
+                    report.0.push(check(Some(BoundAddress::Tcp(addr)), status));
+                },
+            },
+            #[cfg(unix)]
+            ListenAddress::Unix(path) => {
+                let addr = Some(BoundAddress::Unix(path.clone()));
+                report.0.push(match crate::unix::preflight(&listener, path) {
+                    Ok(result) => check(addr, PreflightStatus::from_bind(result)),
+                    Err(err) => check(addr, PreflightStatus::Invalid(err)),
+                });
+            },
+            #[cfg(not(unix))]
+            ListenAddress::Unix(_) => report.0.push(check(None, PreflightStatus::Invalid(
+                InvalidValue! {
+                    expected: "no `unix` listeners on platforms other than Unix",
+                    got: &listener.address,
+                }
+            ))),
+            ListenAddress::Activation(_) => report.0.push(check(None, PreflightStatus::Skipped)),
+        }
+    }
+    report
+}
+
+fn bind(addr: SocketAddr, options: &SocketOptions) -> io::Result<()> {
+    if options == &SocketOptions::default() {
+        TcpListener::bind(addr).map(drop)
+    } else {
+        crate::socket::bind(addr, options, 1).map(drop)
+    }
+}
@@ -0,0 +1,64 @@
+//! Dropping the privileges of the process once its sockets are bound
+
+use crate::actix::ProcessSettings;
+use crate::error::{AtError, AtResult};
+use crate::unix;
+use std::ffi::CString;
+use std::io;
+use std::os::unix::ffi::OsStrExt;
+use std::path::Path;
+
+
+/// Apply `process`: Change the root directory to `chroot`, and then switch
+/// to `group` and `user`.  The ids are looked up first, while the user and
+/// group databases are still reachable.  Without a `group`, the primary
+/// group of `user` is used.  Supplementary groups are always dropped when
+/// switching groups.
+pub(crate) fn drop_privileges(process: &ProcessSettings) -> AtResult<()> {
+    let uid = process.user.as_deref().map(unix::user_id).transpose()?;
+    let gid = match (&process.group, &process.user) {
+        (Some(group), _) => Some(unix::group_id(group)?),
+        (None, Some(user)) => Some(unix::user_group_id(user)?),
+        (None, None) => None,
+    };
+    if let Some(path) = &process.chroot {
+        chroot(path).map_err(|error| AtError::ChrootError {
+            path: path.clone(),
+            error: error.into(),
+        })?;
+    }
+    let error = |what, id| move |error: io::Error| AtError::DropPrivilegesError {
+        what,
+        id,
+        error: error.into(),
+    };
+    if let Some(gid) = gid {
+        // SAFETY: `gid` is a single group id, and its length is passed along.
+        check(unsafe { libc::setgroups(1, &gid) })
+            .map_err(error("supplementary groups", gid))?;
+        check(unsafe { libc::setgid(gid) }).map_err(error("group", gid))?;
+    }
+    if let Some(uid) = uid {
+        check(unsafe { libc::setuid(uid) }).map_err(error("user", uid))?;
+        // Regaining root must be impossible after dropping it:
+        if uid != 0 && unsafe { libc::setuid(0) } == 0 {
+            return Err(error("user", uid)(io::Error::other("root could be regained")));
+        }
+    }
+    Ok(())
+}
+
+fn chroot(path: &Path) -> io::Result<()> {
+    let c_path = CString::new(path.as_os_str().as_bytes())
+        .map_err(|err| io::Error::new(io::ErrorKind::InvalidInput, err))?;
+    // SAFETY: `c_path` is a valid C string.
+    check(unsafe { libc::chroot(c_path.as_ptr()) })?;
+    std::env::set_current_dir("/")
+}
+
+fn check(result: libc::c_int) -> io::Result<()> {
+    match result {
+        0 => Ok(()),
+        _ => Err(io::Error::last_os_error()),
+    }
+}
@@ -0,0 +1,220 @@
+//! Binding TCP listeners with the `SocketOptions` of a `Listener`
+
+use crate::actix::{SocketOptions, Timeout};
+use socket2::{Domain, Protocol, Socket, Type};
+use std::io;
+use std::net::{SocketAddr, TcpListener};
+use std::time::Duration;
+
+
+/// Create a socket for `addr`, set `options` on it, and then bind it and
+/// start listening with `backlog`.
+pub(crate) fn bind(
+    addr: SocketAddr,
+    options: &SocketOptions,
+    backlog: i32,
+) -> io::Result<TcpListener> {
+    let domain = if addr.is_ipv6() { Domain::ipv6() } else { Domain::ipv4() };
+    let socket = Socket::new(domain, Type::stream(), Some(Protocol::tcp()))?;
+    socket.set_reuse_address(options.reuse_address.unwrap_or(cfg!(unix)))?;
+    if let Some(reuse_port) = options.reuse_port {
+        set_reuse_port(&socket, reuse_port)?;
+    }
+    if let Some(nodelay) = options.nodelay {
+        socket.set_nodelay(nodelay)?;
+    }
+    match options.ipv6_only {
+        Some(ipv6_only) if addr.is_ipv6() => socket.set_only_v6(ipv6_only)?,
+        _ => {},
+    }
+    if let Some(size) = options.send_buffer_size {
+        socket.set_send_buffer_size(size)?;
+    }
+    if let Some(size) = options.recv_buffer_size {
+        socket.set_recv_buffer_size(size)?;
+    }
+    set_keepalive(&socket, options)?;
+    socket.bind(&addr.into())?;
+    socket.listen(backlog)?;
+    Ok(socket.into_tcp_listener())
+}
+
+#[cfg(all(unix, not(any(target_os = "solaris", target_os = "illumos"))))]
+fn set_reuse_port(socket: &Socket, reuse_port: bool) -> io::Result<()> {
+    socket.set_reuse_port(reuse_port)
+}
+
+#[cfg(not(all(unix, not(any(target_os = "solaris", target_os = "illumos")))))]
+fn set_reuse_port(_: &Socket, _: bool) -> io::Result<()> {
+    Err(unsupported("reuse-port"))
+}
+
+/// Enable `SO_KEEPALIVE` if any of the keepalive options is set, and then
+/// set those options.
+fn set_keepalive(socket: &Socket, options: &SocketOptions) -> io::Result<()> {
+    let seconds = |timeout: &Timeout| match timeout {
+        Timeout::Default => None,
+        Timeout::Seconds(n) => Some(*n as u64),
+        // NOTE: `Listener::validate()` rejects milliseconds.
+        Timeout::Milliseconds(n) => Some(*n as u64 / 1000),
+    };
+    let idle = seconds(&options.keepalive_idle);
+    let interval = seconds(&options.keepalive_interval);
+    let count = options.keepalive_count;
+    if idle.is_none() && interval.is_none() && count.is_none() {
+        return Ok(());
+    }
+    match idle {
+        Some(idle) => socket.set_keepalive(Some(Duration::from_secs(idle)))?,
+        None => enable_keepalive(socket)?,
+    }
+    if let Some(interval) = interval {
+        set_keepalive_probes(socket, KeepaliveProbes::Interval(interval))?;
+    }
+    if let Some(count) = count {
+        set_keepalive_probes(socket, KeepaliveProbes::Count(count))?;
+    }
+    Ok(())
+}
+
+enum KeepaliveProbes {
+    Interval(u64),
+    Count(u32),
+}
+
+#[cfg(unix)]
+fn enable_keepalive(socket: &Socket) -> io::Result<()> {
+    setsockopt(socket, libc::SOL_SOCKET, libc::SO_KEEPALIVE, 1)
+}
+
+#[cfg(not(unix))]
+fn enable_keepalive(_: &Socket) -> io::Result<()> {
+    Err(unsupported("keepalive-interval and keepalive-count without keepalive-idle"))
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn set_keepalive_probes(socket: &Socket, probes: KeepaliveProbes) -> io::Result<()> {
+    use std::convert::TryFrom;
+    match probes {
+        KeepaliveProbes::Interval(seconds) => {
+            let seconds = libc::c_int::try_from(seconds)
+                .map_err(|_| io::Error::from(io::ErrorKind::InvalidInput))?;
+            setsockopt(socket, libc::IPPROTO_TCP, libc::TCP_KEEPINTVL, seconds)
+        },
+        KeepaliveProbes::Count(count) => {
+            let count = libc::c_int::try_from(count)
+                .map_err(|_| io::Error::from(io::ErrorKind::InvalidInput))?;
+            setsockopt(socket, libc::IPPROTO_TCP, libc::TCP_KEEPCNT, count)
+        },
+    }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+fn set_keepalive_probes(_: &Socket, probes: KeepaliveProbes) -> io::Result<()> {
+    Err(unsupported(match probes {
+        KeepaliveProbes::Interval(_) => "keepalive-interval",
+        KeepaliveProbes::Count(_) => "keepalive-count",
+    }))
+}
+
+#[cfg(unix)]
+fn setsockopt(
+    socket: &Socket,
+    level: libc::c_int,
+    option: libc::c_int,
+    value: libc::c_int,
+) -> io::Result<()> {
+    use std::os::unix::io::AsRawFd;
+    // SAFETY: `value` is a valid `c_int`, and its size is passed along.
+    match unsafe {
+        libc::setsockopt(socket.as_raw_fd(), level, option,
+                         &value as *const _ as *const libc::c_void,
+                         std::mem::size_of::<libc::c_int>() as libc::socklen_t)
+    } {
+        0 => Ok(()),
+        _ => Err(io::Error::last_os_error()),
+    }
+}
+
+#[allow(dead_code)] // Only used on some platforms
+fn unsupported(option: &str) -> io::Error {
+    io::Error::other(format!("`{}` isn't supported on this platform", option))
+}
+
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    #![allow(non_snake_case)]
+
+    use super::*;
+    use std::mem;
+    use std::os::unix::io::AsRawFd;
+
+    fn getsockopt(listener: &TcpListener, level: libc::c_int, option: libc::c_int) -> i32 {
+        let mut value: libc::c_int = 0;
+        let mut len = mem::size_of::<libc::c_int>() as libc::socklen_t;
+        // SAFETY: `value` and `len` describe a valid `c_int` buffer.
+        let result = unsafe {
+            libc::getsockopt(listener.as_raw_fd(), level, option,
+                             &mut value as *mut _ as *mut libc::c_void, &mut len)
+        };
+        assert_eq!(result, 0, "{}", io::Error::last_os_error());
+        value
+    }
+
+    #[test]
+    fn bind__options() -> io::Result<()> {
+        let options = SocketOptions {
+            reuse_port: Some(true),
+            reuse_address: Some(false),
+            nodelay: Some(true),
+            ipv6_only: Some(true),
+            keepalive_idle: Timeout::Seconds(30),
+            keepalive_interval: Timeout::Seconds(5),
+            keepalive_count: Some(3),
+            send_buffer_size: Some(64 * 1024),
+            recv_buffer_size: Some(64 * 1024),
+        };
+        let listener = match bind("[::1]:0".parse().unwrap(), &options, 16) {
+            Err(err) if err.kind() == io::ErrorKind::AddrNotAvailable => return Ok(()),
+            result => result?,
+        };
+        assert_eq!(getsockopt(&listener, libc::SOL_SOCKET, libc::SO_REUSEPORT), 1);
+        assert_eq!(getsockopt(&listener, libc::SOL_SOCKET, libc::SO_REUSEADDR), 0);
+        assert_eq!(getsockopt(&listener, libc::IPPROTO_TCP, libc::TCP_NODELAY), 1);
+        assert_eq!(getsockopt(&listener, libc::IPPROTO_IPV6, libc::IPV6_V6ONLY), 1);
+        assert_eq!(getsockopt(&listener, libc::SOL_SOCKET, libc::SO_KEEPALIVE), 1);
+        assert_eq!(getsockopt(&listener, libc::IPPROTO_TCP, libc::TCP_KEEPIDLE), 30);
+        assert_eq!(getsockopt(&listener, libc::IPPROTO_TCP, libc::TCP_KEEPINTVL), 5);
+        assert_eq!(getsockopt(&listener, libc::IPPROTO_TCP, libc::TCP_KEEPCNT), 3);
+        // NOTE: Linux doubles the buffer sizes to allow for bookkeeping overhead.
+        assert!(getsockopt(&listener, libc::SOL_SOCKET, libc::SO_SNDBUF) >= 64 * 1024);
+        assert!(getsockopt(&listener, libc::SOL_SOCKET, libc::SO_RCVBUF) >= 64 * 1024);
+        Ok(())
+    }
+
+    #[test]
+    fn bind__defaults() -> io::Result<()> {
+        let options = SocketOptions {
+            keepalive_count: Some(4),
+            ..SocketOptions::default()
+        };
+        let listener = bind("127.0.0.1:0".parse().unwrap(), &options, 16)?;
+        assert_eq!(getsockopt(&listener, libc::SOL_SOCKET, libc::SO_REUSEPORT), 0);
+        assert_eq!(getsockopt(&listener, libc::SOL_SOCKET, libc::SO_REUSEADDR), 1);
+        assert_eq!(getsockopt(&listener, libc::IPPROTO_TCP, libc::TCP_NODELAY), 0);
+        assert_eq!(getsockopt(&listener, libc::SOL_SOCKET, libc::SO_KEEPALIVE), 1);
+        assert_eq!(getsockopt(&listener, libc::IPPROTO_TCP, libc::TCP_KEEPCNT), 4);
+        assert_ne!(listener.local_addr()?.port(), 0);
+        Ok(())
+    }
+
+    #[test]
+    fn bind__reuse_port() -> io::Result<()> {
+        let options = SocketOptions { reuse_port: Some(true), ..SocketOptions::default() };
+        let first = bind("127.0.0.1:0".parse().unwrap(), &options, 16)?;
+        let second = bind(first.local_addr()?, &options, 16)?;
+        assert_eq!(first.local_addr()?, second.local_addr()?);
+        Ok(())
+    }
+}
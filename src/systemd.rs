@@ -0,0 +1,401 @@
+//! systemd socket activation support for `ApplySettings`
+
+use crate::error::{AtError, AtResult};
+use std::collections::HashSet;
+use std::env;
+use std::io;
+use std::mem;
+use std::net::TcpListener;
+use std::os::unix::io::{FromRawFd, RawFd};
+use std::os::unix::net::UnixListener;
+
+
+/// The first file descriptor passed by systemd, i.e. `SD_LISTEN_FDS_START`.
+const LISTEN_FDS_START: RawFd = 3;
+
+/// A listening socket inherited from the service manager.
+#[derive(Debug)]
+pub(crate) enum ActivatedSocket {
+    Tcp(TcpListener),
+    Unix(UnixListener),
+}
+
+/// The file descriptors passed to this process by systemd, together with
+/// their names.  Those that are taken by a listener are removed.
+#[derive(Debug)]
+pub(crate) struct ActivatedSockets {
+    fds: Vec<(String, RawFd)>,
+}
+
+impl ActivatedSockets {
+    /// Read the `LISTEN_PID`, `LISTEN_FDS` and `LISTEN_FDNAMES` environment
+    /// variables, and then remove them so that the file descriptors are
+    /// only used once, and aren't passed on to child processes.
+    pub(crate) fn from_env() -> AtResult<Self> {
+        let var = |name| env::var(name).ok();
+        let fds = parse_env(
+            var("LISTEN_PID").as_deref(),
+            var("LISTEN_FDS").as_deref(),
+            var("LISTEN_FDNAMES").as_deref(),
+            std::process::id(),
+        )?;
+        for name in &["LISTEN_PID", "LISTEN_FDS", "LISTEN_FDNAMES"] {
+            env::remove_var(name);
+        }
+        Ok(Self { fds })
+    }
+
+    /// Take the sockets named `fd_name` or, without a name, the sockets
+    /// whose names aren't in `claimed` i.e. not used by other listeners.
+    pub(crate) fn take(
+        &mut self,
+        fd_name: Option<&str>,
+        claimed: &HashSet<&str>,
+    ) -> AtResult<Vec<ActivatedSocket>> {
+        let (taken, rest): (Vec<_>, Vec<_>) = self.fds.drain(..)
+            .partition(|(name, _)| match fd_name {
+                Some(fd_name) => name == fd_name,
+                None => !claimed.contains(name.as_str()),
+            });
+        self.fds = rest;
+        if taken.is_empty() {
+            return Err(AtError::SocketActivationError(match fd_name {
+                Some(fd_name) => format!("no inherited socket named {:?}", fd_name),
+                None => "no inherited sockets left for an unnamed listener".to_string(),
+            }));
+        }
+        taken.into_iter().map(|(_, fd)| activated_socket(fd)).collect()
+    }
+
+    /// The names of the sockets that haven't been taken by any listener.
+    pub(crate) fn unused(&self) -> impl Iterator<Item = &str> {
+        self.fds.iter().map(|(name, _)| name.as_str())
+    }
+}
+
+/// Determine the inherited file descriptors and their names.  These are
+/// only meant for this process if `listen_pid` equals `pid`.  Unnamed file
+/// descriptors are called `"unknown"`, like systemd does.
+fn parse_env(
+    listen_pid: Option<&str>,
+    listen_fds: Option<&str>,
+    listen_fdnames: Option<&str>,
+    pid: u32,
+) -> AtResult<Vec<(String, RawFd)>> {
+    let error = |msg: String| AtError::SocketActivationError(msg);
+    let (listen_pid, listen_fds) = match (listen_pid, listen_fds) {
+        (Some(listen_pid), Some(listen_fds)) => (listen_pid, listen_fds),
+        _ => return Err(error("LISTEN_PID and LISTEN_FDS aren't set; \
+                               the process wasn't socket-activated".to_string())),
+    };
+    match listen_pid.parse::<u32>() {
+        Ok(listen_pid) if listen_pid == pid => {},
+        Ok(listen_pid) => return Err(error(format!(
+            "LISTEN_PID is {}, but the pid of this process is {}", listen_pid, pid
+        ))),
+        Err(_) => return Err(error(format!("invalid LISTEN_PID: {:?}", listen_pid))),
+    }
+    let count: RawFd = listen_fds.parse()
+        .ok()
+        .filter(|count| (0..=RawFd::MAX - LISTEN_FDS_START).contains(count))
+        .ok_or_else(|| error(format!("invalid LISTEN_FDS: {:?}", listen_fds)))?;
+    let mut names: Vec<String> = match listen_fdnames {
+        Some(names) => names.split(':').map(String::from).collect(),
+        None => vec![],
+    };
+    if listen_fdnames.is_some() && names.len() != count as usize {
+        return Err(error(format!(
+            "LISTEN_FDNAMES has {} names for {} file descriptors", names.len(), count
+        )));
+    }
+    names.resize(count as usize, "unknown".to_string());
+    Ok(names.into_iter().zip(LISTEN_FDS_START..).collect())
+}
+
+/// Take ownership of `fd`, after checking that it's a listening stream
+/// socket.  The close-on-exec flag is set, like for any other socket.
+fn activated_socket(fd: RawFd) -> AtResult<ActivatedSocket> {
+    let error = |msg: String| {
+        AtError::SocketActivationError(format!("file descriptor {}: {}", fd, msg))
+    };
+    let getsockopt = |option| {
+        let mut value: libc::c_int = 0;
+        let mut len = mem::size_of::<libc::c_int>() as libc::socklen_t;
+        // SAFETY: `value` and `len` describe a valid `c_int` buffer.
+        match unsafe {
+            libc::getsockopt(fd, libc::SOL_SOCKET, option,
+                             &mut value as *mut _ as *mut libc::c_void, &mut len)
+        } {
+            0 => Ok(value),
+            _ => Err(error(io::Error::last_os_error().to_string())),
+        }
+    };
+    if getsockopt(libc::SO_TYPE)? != libc::SOCK_STREAM
+        || getsockopt(libc::SO_ACCEPTCONN)? == 0 {
+        return Err(error("not a listening stream socket".to_string()));
+    }
+    // SAFETY: `sockaddr_storage` is a plain C struct, for which all zeroes
+    //         is a valid value, and it's large enough for any address.
+    let mut address: libc::sockaddr_storage = unsafe { mem::zeroed() };
+    let mut len = mem::size_of::<libc::sockaddr_storage>() as libc::socklen_t;
+    if unsafe {
+        libc::getsockname(fd, &mut address as *mut _ as *mut libc::sockaddr, &mut len)
+    } != 0 {
+        return Err(error(io::Error::last_os_error().to_string()));
+    }
+    if unsafe { libc::fcntl(fd, libc::F_SETFD, libc::FD_CLOEXEC) } != 0 {
+        return Err(error(io::Error::last_os_error().to_string()));
+    }
+    // SAFETY: The service manager passed `fd` to this process to be used
+    //         as a listening socket, and it's taken only once.
+    match libc::c_int::from(address.ss_family) {
+        libc::AF_INET | libc::AF_INET6 =>
+            Ok(ActivatedSocket::Tcp(unsafe { TcpListener::from_raw_fd(fd) })),
+        libc::AF_UNIX =>
+            Ok(ActivatedSocket::Unix(unsafe { UnixListener::from_raw_fd(fd) })),
+        family => Err(error(format!("unsupported address family {}", family))),
+    }
+}
+
+/// Keep the file of the inherited Unix `socket` in place for as long as
+/// the current actix system runs.
+///
+/// NOTE: actix-server removes the file at the address of a Unix socket
+///       whenever it stops accepting connections on it, e.g. while its
+///       workers are starting or under backpressure.  The address of an
+///       inherited socket is the file created by the service manager, so a
+///       hard link to it is made next to it, from which the file is
+///       restored as soon as it's removed.  The link is removed again once
+///       the system stops, leaving the file in place, as the service
+///       manager still listens on the socket.  Without write access to the
+///       directory, neither can the link be made nor the file be removed.
+#[cfg(target_os = "linux")]
+pub(crate) fn keep_socket_file(socket: &UnixListener) -> io::Result<()> {
+    use std::ffi::CString;
+    use std::fs::File;
+    use std::os::unix::ffi::OsStrExt;
+    use std::path::Path;
+    let path = match socket.local_addr()?.as_pathname() {
+        Some(path) => path.to_path_buf(),
+        None => return Ok(()), // An unnamed socket has no file to remove
+    };
+    let (dir, name) = match (path.parent(), path.file_name()) {
+        (Some(dir), Some(name)) if dir != Path::new("") => (dir, name),
+        (_, Some(name)) => (Path::new("."), name),
+        (_, None) => return Ok(()),
+    };
+    let c_string = |bytes: &[u8]| CString::new(bytes)
+        .map_err(|_| io::Error::from(io::ErrorKind::InvalidInput));
+    let c_dir = c_string(dir.as_os_str().as_bytes())?;
+    // NOTE: The directory is opened, so that the file is still restored
+    //       after changing the root directory.
+    // SAFETY: The path is a valid C string, and the file descriptor that
+    //         is returned is owned by the `File` that wraps it.
+    let socket_file = SocketFile {
+        dir: unsafe {
+            File::from_raw_fd(check(libc::open(
+                c_dir.as_ptr(), libc::O_PATH | libc::O_DIRECTORY | libc::O_CLOEXEC
+            ))?)
+        },
+        name: c_string(name.as_bytes())?,
+        link: c_string(&[b".", name.as_bytes(), b".keep"].concat())?,
+        path,
+    };
+    match socket_file.link() {
+        Err(err) if err.kind() == io::ErrorKind::PermissionDenied => return Ok(()),
+        result => result?,
+    };
+    let keeper = SocketFileKeeper::start(socket_file, &c_dir)?;
+    // NOTE: The runtime of the system drops the future when it stops.
+    actix_rt::spawn(async move {
+        let _keeper = keeper;
+        std::future::pending::<()>().await
+    });
+    Ok(())
+}
+
+#[cfg(target_os = "linux")]
+fn check(result: libc::c_int) -> io::Result<libc::c_int> {
+    match result {
+        -1 => Err(io::Error::last_os_error()),
+        result => Ok(result),
+    }
+}
+
+/// The file of an inherited Unix socket at `path`, i.e. `name` in `dir`,
+/// together with a hard `link` to it in the same directory, which is
+/// removed when this is dropped.
+#[cfg(target_os = "linux")]
+struct SocketFile {
+    dir: std::fs::File,
+    name: std::ffi::CString,
+    link: std::ffi::CString,
+    path: std::path::PathBuf,
+}
+
+#[cfg(target_os = "linux")]
+impl SocketFile {
+    /// Replace the `link` by a new one to the socket file.
+    fn link(&self) -> io::Result<()> {
+        use std::os::unix::io::AsRawFd;
+        // SAFETY: The names are valid C strings, and `dir` is open.
+        unsafe {
+            libc::unlinkat(self.dir.as_raw_fd(), self.link.as_ptr(), 0);
+            check(libc::linkat(
+                self.dir.as_raw_fd(), self.name.as_ptr(), self.dir.as_raw_fd(), self.link.as_ptr(), 0
+            )).map(drop)
+        }
+    }
+
+    /// Restore the socket file from the `link`, returning whether it was
+    /// missing.
+    fn restore(&self) -> bool {
+        use std::os::unix::io::AsRawFd;
+        // SAFETY: The names are valid C strings, and `dir` is open.
+        unsafe {
+            libc::linkat(
+                self.dir.as_raw_fd(), self.link.as_ptr(), self.dir.as_raw_fd(), self.name.as_ptr(), 0
+            ) == 0
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+impl Drop for SocketFile {
+    fn drop(&mut self) {
+        use std::os::unix::io::AsRawFd;
+        // SAFETY: The name is a valid C string, and `dir` is open.
+        unsafe { libc::unlinkat(self.dir.as_raw_fd(), self.link.as_ptr(), 0) };
+    }
+}
+
+/// Restores a `SocketFile` on a background thread whenever it's removed,
+/// until this is dropped.
+#[cfg(target_os = "linux")]
+struct SocketFileKeeper {
+    socket_file: std::sync::Arc<SocketFile>,
+    /// Closing this wakes up the thread, which then stops.
+    stop: Option<std::os::unix::net::UnixStream>,
+    thread: Option<std::thread::JoinHandle<()>>,
+}
+
+#[cfg(target_os = "linux")]
+impl SocketFileKeeper {
+    fn start(socket_file: SocketFile, c_dir: &std::ffi::CStr) -> io::Result<Self> {
+        use std::fs::File;
+        use std::io::Read;
+        use std::os::unix::io::AsRawFd;
+        use std::os::unix::net::UnixStream;
+        use std::sync::Arc;
+        let mut inotify = unsafe { File::from_raw_fd(check(libc::inotify_init1(libc::IN_CLOEXEC))?) };
+        check(unsafe {
+            libc::inotify_add_watch(inotify.as_raw_fd(), c_dir.as_ptr(),
+                                    libc::IN_DELETE | libc::IN_MOVED_FROM)
+        })?;
+        let (stop, stopped) = UnixStream::pair()?;
+        let socket_file = Arc::new(socket_file);
+        let kept = Arc::clone(&socket_file);
+        let thread = std::thread::Builder::new().name("keep-socket-file".to_string()).spawn(move || {
+            let mut fds = [
+                libc::pollfd { fd: inotify.as_raw_fd(), events: libc::POLLIN, revents: 0 },
+                libc::pollfd { fd: stopped.as_raw_fd(), events: libc::POLLIN, revents: 0 },
+            ];
+            // Only the file at `path` matters, so the events aren't parsed:
+            let mut events = [0; 4096];
+            loop {
+                let polled = check(unsafe { libc::poll(fds.as_mut_ptr(), 2, -1) })
+                    .and_then(|_| match fds[1].revents {
+                        0 => inotify.read(&mut events).map(Some),
+                        _ => Ok(None),
+                    });
+                match polled {
+                    Ok(Some(_)) => {},
+                    Ok(None) => return,
+                    Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+                    Err(err) => {
+                        log::error!("Stopped keeping socket file {} in place: {}",
+                                    kept.path.display(), err);
+                        return;
+                    },
+                }
+                if kept.restore() {
+                    log::debug!("Restored socket file {}", kept.path.display());
+                }
+            }
+        })?;
+        Ok(Self { socket_file, stop: Some(stop), thread: Some(thread) })
+    }
+}
+
+#[cfg(target_os = "linux")]
+impl Drop for SocketFileKeeper {
+    fn drop(&mut self) {
+        self.stop.take();
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+        // actix-server also removes the file when it stops:
+        self.socket_file.restore();
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    #![allow(non_snake_case)]
+
+    use super::*;
+
+    #[test]
+    fn parse_env__names() {
+        let fds = parse_env(Some("42"), Some("3"), Some("http:https:admin"), 42).unwrap();
+        assert_eq!(fds, vec![
+            ("http".to_string(), 3),
+            ("https".to_string(), 4),
+            ("admin".to_string(), 5),
+        ]);
+    }
+
+    #[test]
+    fn parse_env__unnamed() {
+        let fds = parse_env(Some("42"), Some("2"), None, 42).unwrap();
+        assert_eq!(fds, vec![("unknown".to_string(), 3), ("unknown".to_string(), 4)]);
+    }
+
+    #[test]
+    fn parse_env__errors() {
+        let cases = [
+            (None,        Some("1"),  None),
+            (Some("42"),  None,       None),
+            (Some("43"),  Some("1"),  None),
+            (Some("pid"), Some("1"),  None),
+            (Some("42"),  Some("-1"), None),
+            (Some("42"),  Some("2"),  Some("http")),
+        ];
+        for &(listen_pid, listen_fds, listen_fdnames) in &cases {
+            match parse_env(listen_pid, listen_fds, listen_fdnames, 42) {
+                Err(AtError::SocketActivationError(_)) => {},
+                result => panic!("Expected a SocketActivationError for {:?}, got {:?}",
+                                 (listen_pid, listen_fds, listen_fdnames), result),
+            }
+        }
+    }
+
+    #[test]
+    fn take__claimed_names() {
+        // NOTE: Nothing is taken here, so the file descriptors are placeholders.
+        let mut sockets = ActivatedSockets {
+            fds: vec![("http".to_string(), -1), ("other".to_string(), -1)],
+        };
+        let claimed: HashSet<&str> = ["http", "other"].iter().copied().collect();
+        match sockets.take(None, &claimed) {
+            Err(AtError::SocketActivationError(_)) => {},
+            result => panic!("Expected a SocketActivationError, got {:?}", result),
+        }
+        match sockets.take(Some("admin"), &claimed) {
+            Err(AtError::SocketActivationError(msg)) => assert!(msg.contains("admin")),
+            result => panic!("Expected a SocketActivationError, got {:?}", result),
+        }
+        assert_eq!(sockets.unused().collect::<Vec<_>>(), ["http", "other"]);
+    }
+}
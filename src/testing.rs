@@ -0,0 +1,373 @@
+//! Helpers shared by the unit tests of this crate
+
+#![allow(dead_code)] // Not every helper is used under every feature set
+
+use crate::{Address, ApplySettings, Endpoint, Host, Settings};
+use actix_web::{web, App, HttpServer};
+use std::io;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::thread;
+
+/// An address that lets the OS pick a free port on the loopback interface.
+pub fn localhost_address() -> Address {
+    Address { host: Host::Ip([127, 0, 0, 1].into()), port: 0 }
+}
+
+/// `hosts` with a single address that lets the OS pick a free port on the
+/// loopback interface.
+pub fn localhost_port_0() -> Vec<Endpoint> {
+    vec![Endpoint::Address(localhost_address())]
+}
+
+/// Start an `HttpServer` configured by `settings` on a background thread,
+/// and return the addresses it is listening on.  Every request to `/` is
+/// answered with a `200 OK`.
+pub fn spawn_server(settings: Settings) -> Vec<SocketAddr> {
+    let (tx, rx) = mpsc::channel();
+    thread::spawn(move || {
+        let sys = actix_rt::System::new("test-server");
+        let server = HttpServer::new(|| {
+            App::new().route("/", web::get().to(|| async { "OK" }))
+        })
+            .disable_signals()
+            .apply_settings(&settings);
+        tx.send(server.addrs()).expect("Failed to report server addresses");
+        server.run();
+        sys.run()
+    });
+    rx.recv().expect("Failed to start test server")
+}
+
+/// The environment variable holding the file descriptor to which a child
+/// process started by `spawn_socket_activated` or `run_in_child` reports.
+#[cfg(unix)]
+const REPORT_FD: &str = "ACTIX_SETTINGS_REPORT_FD";
+
+/// Run the ignored test called `test` in a child process, passing it the
+/// listening `sockets` like systemd does for socket activation: as file
+/// descriptors 3, 4, ... named in `LISTEN_FDNAMES`.  `LISTEN_PID` can't be
+/// known before spawning, so the child has to set it itself.  Waits for the
+/// child to `report` that it's `READY`.
+#[cfg(unix)]
+pub fn spawn_socket_activated(
+    test: &str,
+    sockets: &[(&str, std::os::unix::io::RawFd)],
+) -> io::Result<std::process::Child> {
+    use std::io::BufRead;
+    use std::os::unix::io::AsRawFd;
+    use std::process::{Command, Stdio};
+    let names: Vec<&str> = sockets.iter().map(|&(name, _)| name).collect();
+    let (reports, report_fd) = report_pipe()?;
+    let mut fds: Vec<_> = sockets.iter().map(|&(_, fd)| fd).collect();
+    fds.push(report_fd.as_raw_fd());
+    let mut command = Command::new(std::env::current_exe()?);
+    command.args([test, "--exact", "--ignored", "--nocapture", "--test-threads=1"])
+        .env("ACTIX_SETTINGS_SOCKET_ACTIVATED", "1")
+        .env("LISTEN_FDS", sockets.len().to_string())
+        .env("LISTEN_FDNAMES", names.join(":"))
+        .env_remove("LISTEN_PID")
+        .env(REPORT_FD, (3 + sockets.len()).to_string())
+        .stdout(Stdio::null());
+    inherit_fds(&mut command, fds);
+    let mut child = command.spawn()?;
+    drop(report_fd);
+    for line in io::BufReader::new(reports).lines() {
+        if line? == "READY" {
+            return Ok(child);
+        }
+    }
+    let _ = child.kill();
+    Err(io::Error::other(format!("{} exited before it was ready", test)))
+}
+
+/// Run the ignored `test` of this test binary in a child process with the
+/// environment variables `envs`, and return what it reported.  Fails if the
+/// test failed.
+#[cfg(unix)]
+pub fn run_in_child(test: &str, envs: &[(&str, &std::ffi::OsStr)]) -> io::Result<String> {
+    use std::io::Read;
+    use std::os::unix::io::AsRawFd;
+    let (mut reports, report_fd) = report_pipe()?;
+    let mut command = std::process::Command::new(std::env::current_exe()?);
+    command.args([test, "--exact", "--ignored", "--nocapture", "--test-threads=1"])
+        .envs(envs.iter().copied())
+        .env(REPORT_FD, "3");
+    inherit_fds(&mut command, vec![report_fd.as_raw_fd()]);
+    let output = command.output()?;
+    drop(report_fd);
+    if !output.status.success() {
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(io::Error::other(format!("{} failed: {}{}", test, stdout, stderr)));
+    }
+    let mut reported = String::new();
+    reports.read_to_string(&mut reported)?;
+    Ok(reported)
+}
+
+/// Report `message` as a line to the test that started this child process
+/// with `spawn_socket_activated` or `run_in_child`.  This keeps working
+/// after changing the root directory or dropping privileges.
+#[cfg(unix)]
+pub fn report(message: &str) -> io::Result<()> {
+    use std::io::Write;
+    use std::os::unix::io::FromRawFd;
+    let fd = std::env::var(REPORT_FD).ok().and_then(|fd| fd.parse().ok())
+        .ok_or_else(|| io::Error::other(format!("{} isn't set", REPORT_FD)))?;
+    // SAFETY: The file descriptor was inherited for this purpose, and it's
+    //         left open for later reports.
+    let mut file = std::mem::ManuallyDrop::new(unsafe { std::fs::File::from_raw_fd(fd) });
+    writeln!(file, "{}", message)
+}
+
+/// A pipe whose ends are closed on exec, so that only the child process
+/// that `inherit_fds` its write end can `report` to it.
+#[cfg(unix)]
+fn report_pipe() -> io::Result<(std::fs::File, std::fs::File)> {
+    use std::os::unix::io::FromRawFd;
+    let mut fds = [0; 2];
+    if unsafe { libc::pipe2(fds.as_mut_ptr(), libc::O_CLOEXEC) } == -1 {
+        return Err(io::Error::last_os_error());
+    }
+    // SAFETY: `pipe2()` returned two new file descriptors.
+    Ok(unsafe { (std::fs::File::from_raw_fd(fds[0]), std::fs::File::from_raw_fd(fds[1])) })
+}
+
+/// Let the child process spawned by `command` inherit `fds` as its file
+/// descriptors 3, 4, ...
+#[cfg(unix)]
+fn inherit_fds(command: &mut std::process::Command, fds: Vec<std::os::unix::io::RawFd>) {
+    use std::os::unix::process::CommandExt;
+    assert!(fds.len() <= 16, "Too many file descriptors");
+    // SAFETY: Only async-signal-safe functions are called after forking.
+    //         The fds are first moved out of the way, so that moving them
+    //         to 3, 4, ... can't overwrite one that's yet to be moved.
+    unsafe {
+        command.pre_exec(move || {
+            let mut high = [0; 16];
+            for (i, &fd) in fds.iter().enumerate() {
+                high[i] = libc::fcntl(fd, libc::F_DUPFD_CLOEXEC, 100);
+                if high[i] < 0 { return Err(io::Error::last_os_error()); }
+            }
+            for (i, &fd) in high[..fds.len()].iter().enumerate() {
+                if libc::dup2(fd, 3 + i as libc::c_int) < 0 {
+                    return Err(io::Error::last_os_error());
+                }
+            }
+            Ok(())
+        });
+    }
+}
+
+/// Poll `condition` until it holds, giving up after 5 seconds.
+pub fn eventually<F: FnMut() -> bool>(mut condition: F) -> bool {
+    let deadline = std::time::Instant::now() + std::time::Duration::from_secs(5);
+    while std::time::Instant::now() < deadline {
+        if condition() { return true; }
+        thread::sleep(std::time::Duration::from_millis(20));
+    }
+    false
+}
+
+/// A self-signed certificate together with its private key, both PEM-encoded.
+pub struct SelfSignedCert {
+    pub cert_pem: String,
+    pub key_pem: String,
+}
+
+impl SelfSignedCert {
+    pub fn generate(names: &[&str]) -> Self {
+        let names: Vec<String> = names.iter().map(|name| name.to_string()).collect();
+        let cert = rcgen::generate_simple_self_signed(names)
+            .expect("Failed to generate self-signed certificate");
+        Self {
+            cert_pem: cert.serialize_pem().expect("Failed to serialize certificate"),
+            key_pem: cert.serialize_private_key_pem(),
+        }
+    }
+
+    /// Write the certificate and key to `cert.pem` and `key.pem` in `dir`,
+    /// and return the paths of both files.
+    pub fn write_to(&self, dir: &Path) -> io::Result<(PathBuf, PathBuf)> {
+        self.write_as(dir, "")
+    }
+
+    /// The private key, encrypted with `passphrase` in the PKCS#8 format.
+    #[cfg(feature = "openssl")]
+    pub fn encrypted_key_pem(&self, passphrase: &str) -> Vec<u8> {
+        let key = openssl::pkey::PKey::private_key_from_pem(self.key_pem.as_bytes())
+            .expect("Failed to parse private key");
+        let cipher = openssl::symm::Cipher::aes_256_cbc();
+        key.private_key_to_pem_pkcs8_passphrase(cipher, passphrase.as_bytes())
+            .expect("Failed to encrypt private key")
+    }
+
+    /// The certificate and private key as a PKCS#12 bundle protected by
+    /// `passphrase`.
+    #[cfg(feature = "openssl")]
+    pub fn to_pkcs12(&self, passphrase: &str) -> Vec<u8> {
+        let cert = openssl::x509::X509::from_pem(self.cert_pem.as_bytes())
+            .expect("Failed to parse certificate");
+        let key = openssl::pkey::PKey::private_key_from_pem(self.key_pem.as_bytes())
+            .expect("Failed to parse private key");
+        let mut builder = openssl::pkcs12::Pkcs12::builder();
+        builder.name("actix-settings").pkey(&key).cert(&cert);
+        builder.build2(passphrase)
+            .and_then(|pkcs12| pkcs12.to_der())
+            .expect("Failed to build PKCS#12 bundle")
+    }
+
+    /// Like `write_to`, but prefixes both file names with `prefix`.
+    pub fn write_as(&self, dir: &Path, prefix: &str) -> io::Result<(PathBuf, PathBuf)> {
+        let cert_path = dir.join(format!("{}cert.pem", prefix));
+        let key_path = dir.join(format!("{}key.pem", prefix));
+        std::fs::write(&cert_path, &self.cert_pem)?;
+        std::fs::write(&key_path, &self.key_pem)?;
+        Ok((cert_path, key_path))
+    }
+}
+
+/// A certificate authority that can issue client certificates.
+pub struct TestCa {
+    ca: rcgen::Certificate,
+    pub cert_pem: String,
+}
+
+impl TestCa {
+    pub fn generate() -> Self {
+        let mut params = rcgen::CertificateParams::new(Vec::<String>::new());
+        params.is_ca = rcgen::IsCa::Ca(rcgen::BasicConstraints::Unconstrained);
+        params.distinguished_name.push(rcgen::DnType::CommonName, "actix-settings test CA");
+        let ca = rcgen::Certificate::from_params(params)
+            .expect("Failed to generate CA certificate");
+        let cert_pem = ca.serialize_pem().expect("Failed to serialize CA certificate");
+        Self { ca, cert_pem }
+    }
+
+    /// Issue a client certificate signed by this CA.
+    pub fn issue_client_cert(&self, common_name: &str) -> SelfSignedCert {
+        let mut params = rcgen::CertificateParams::new(Vec::<String>::new());
+        params.distinguished_name.push(rcgen::DnType::CommonName, common_name);
+        params.extended_key_usages = vec![rcgen::ExtendedKeyUsagePurpose::ClientAuth];
+        let cert = rcgen::Certificate::from_params(params)
+            .expect("Failed to generate client certificate");
+        SelfSignedCert {
+            cert_pem: cert.serialize_pem_with_signer(&self.ca)
+                .expect("Failed to sign client certificate"),
+            key_pem: cert.serialize_private_key_pem(),
+        }
+    }
+
+    /// Write the CA certificate to `ca.pem` in `dir`, and return its path.
+    pub fn write_to(&self, dir: &Path) -> io::Result<PathBuf> {
+        let path = dir.join("ca.pem");
+        std::fs::write(&path, &self.cert_pem)?;
+        Ok(path)
+    }
+}
+
+/// Return the raw HTTP response of the server at `addr` to a `GET /` request.
+pub fn http_get(addr: SocketAddr) -> io::Result<String> {
+    use std::io::{Read, Write};
+    let mut stream = std::net::TcpStream::connect(addr)?;
+    write!(stream, "GET / HTTP/1.1\r\nHost: {}\r\nConnection: close\r\n\r\n", addr)?;
+    let mut response = String::new();
+    stream.read_to_string(&mut response)?;
+    Ok(response)
+}
+
+/// Like `http_get`, but connects to the Unix domain socket at `path`.
+#[cfg(unix)]
+pub fn http_get_unix(path: &Path) -> io::Result<String> {
+    use std::io::{Read, Write};
+    let mut stream = std::os::unix::net::UnixStream::connect(path)?;
+    write!(stream, "GET / HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n")?;
+    let mut response = String::new();
+    stream.read_to_string(&mut response)?;
+    Ok(response)
+}
+
+/// Like `http_get_unix`, but on a background thread, after waiting for the
+/// connection to reach the server.
+#[cfg(unix)]
+pub fn spawn_http_get_unix(path: &Path) -> thread::JoinHandle<io::Result<String>> {
+    let path = path.to_path_buf();
+    let handle = thread::spawn(move || http_get_unix(&path));
+    thread::sleep(std::time::Duration::from_millis(100));
+    handle
+}
+
+/// Perform a TLS handshake with the server at `addr` using `server_name`
+/// for SNI, trusting only `root_pem`, and return the raw HTTP response to
+/// a `GET /` request.  The client side always uses `rustls`, regardless
+/// of the backend the server is using.
+pub fn https_get(addr: SocketAddr, server_name: &str, root_pem: &str) -> io::Result<String> {
+    https_get_as(addr, server_name, root_pem, None)
+}
+
+/// Like `https_get`, but authenticates using `client_cert` if present.
+pub fn https_get_as(
+    addr: SocketAddr,
+    server_name: &str,
+    root_pem: &str,
+    client_cert: Option<&SelfSignedCert>,
+) -> io::Result<String> {
+    use rustls::internal::pemfile::{certs, pkcs8_private_keys};
+    use std::io::{Read, Write};
+    use std::net::TcpStream;
+    use std::sync::Arc;
+    let invalid_data = |msg| io::Error::new(io::ErrorKind::InvalidData, msg);
+    let mut config = rustls::ClientConfig::new();
+    config.root_store.add_pem_file(&mut root_pem.as_bytes())
+        .map_err(|_| invalid_data("bad root cert"))?;
+    if let Some(client_cert) = client_cert {
+        let chain = certs(&mut client_cert.cert_pem.as_bytes())
+            .map_err(|_| invalid_data("bad client cert"))?;
+        let key = pkcs8_private_keys(&mut client_cert.key_pem.as_bytes())
+            .map_err(|_| invalid_data("bad client key"))?
+            .remove(0);
+        config.set_single_client_cert(chain, key)
+            .map_err(|_| invalid_data("bad client cert"))?;
+    }
+    let dns_name = webpki::DNSNameRef::try_from_ascii_str(server_name)
+        .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "bad server name"))?;
+    let mut session = rustls::ClientSession::new(&Arc::new(config), dns_name);
+    let mut socket = TcpStream::connect(addr)?;
+    let mut stream = rustls::Stream::new(&mut session, &mut socket);
+    write!(stream, "GET / HTTP/1.1\r\nHost: {}\r\nConnection: close\r\n\r\n", server_name)?;
+    let mut response = String::new();
+    match stream.read_to_string(&mut response) {
+        // The server may close the connection without sending `close_notify`.
+        Err(err) if err.kind() == io::ErrorKind::ConnectionAborted => Ok(response),
+        Err(err) => Err(err),
+        Ok(_) => Ok(response),
+    }
+}
+
+/// Perform only a TLS handshake with the server at `addr`, trusting only
+/// `root_pem`.  `configure` can adjust the client configuration beforehand.
+pub fn tls_handshake<F>(
+    addr: SocketAddr,
+    server_name: &str,
+    root_pem: &str,
+    configure: F,
+) -> io::Result<rustls::ClientSession>
+where F: FnOnce(&mut rustls::ClientConfig) {
+    use rustls::Session;
+    use std::net::TcpStream;
+    use std::sync::Arc;
+    let mut config = rustls::ClientConfig::new();
+    config.root_store.add_pem_file(&mut root_pem.as_bytes())
+        .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "bad root cert"))?;
+    configure(&mut config);
+    let dns_name = webpki::DNSNameRef::try_from_ascii_str(server_name)
+        .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "bad server name"))?;
+    let mut session = rustls::ClientSession::new(&Arc::new(config), dns_name);
+    let mut socket = TcpStream::connect(addr)?;
+    while session.is_handshaking() {
+        session.complete_io(&mut socket)?;
+    }
+    Ok(session)
+}
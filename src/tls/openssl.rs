@@ -0,0 +1,234 @@
+//! The `openssl` TLS backend
+
+use crate::actix::{CipherSuite, ClientAuth, ClientAuthMode, Ssl, TlsVersion};
+use crate::error::{AtError, AtResult};
+use crate::tls::{KeyFiles, Reloading, SniMap};
+use openssl::error::ErrorStack;
+use openssl::pkcs12::Pkcs12;
+use openssl::pkey::{PKey, Private};
+use openssl::ssl::{
+    AlpnError, NameType, SniError, SslAcceptor, SslAcceptorBuilder, SslContext,
+    SslMethod, SslVerifyMode, SslVersion,
+};
+use openssl::x509::X509;
+use std::sync::Arc;
+
+
+/// Build an `openssl` acceptor from the certificate chains and private key
+/// files named by `ssl`.  The acceptor uses Mozilla's "intermediate"
+/// compatibility profile.
+pub fn openssl_acceptor_builder(ssl: &Ssl) -> AtResult<SslAcceptorBuilder> {
+    OpensslAcceptors::load(ssl)?.acceptor_builder()
+}
+
+/// Creates acceptors that share their key material.  This way, the
+/// certificate files are only watched once regardless of the number of
+/// acceptors.
+pub(crate) struct OpensslAcceptors {
+    ssl: Ssl,
+    contexts: Arc<Reloading<SniMap<SslContext>>>,
+}
+
+impl OpensslAcceptors {
+    pub(crate) fn load(ssl: &Ssl) -> AtResult<Self> {
+        let contexts = Reloading::load(ssl, |ssl| {
+            SniMap::load(ssl, |files| {
+                Ok(acceptor_builder(ssl, files)?.build().into_context())
+            })
+        })?;
+        Ok(Self { ssl: ssl.clone(), contexts })
+    }
+
+    /// Build an acceptor that switches to the `SslContext` matching the
+    /// SNI server name during each handshake.  OpenSSL calls the callback
+    /// even if the client didn't send a server name.
+    pub(crate) fn acceptor_builder(&self) -> AtResult<SslAcceptorBuilder> {
+        let ssl = &self.ssl;
+        let mut builder = acceptor_builder(ssl, KeyFiles::default_of(ssl))?;
+        let contexts = Arc::clone(&self.contexts);
+        builder.set_servername_callback(move |ssl_ref, _alert| {
+            let contexts = contexts.current();
+            let context = contexts.resolve(ssl_ref.servername(NameType::HOST_NAME));
+            ssl_ref.set_ssl_context(context).map_err(|_| SniError::ALERT_FATAL)
+        });
+        Ok(builder)
+    }
+}
+
+/// Build an acceptor that serves the certificate chain and private key in
+/// `files`, and applies the remaining settings in `ssl`.
+fn acceptor_builder(ssl: &Ssl, files: KeyFiles) -> AtResult<SslAcceptorBuilder> {
+    let KeyMaterial { chain, key } = load_key_material(files)?;
+    let mut chain = chain.into_iter();
+    let leaf = chain.next().expect("Certificate chains are never empty");
+    let mut builder = SslAcceptor::mozilla_intermediate_v5(SslMethod::tls())
+        .map_err(tls_error)?;
+    builder.set_certificate(&leaf).map_err(tls_error)?;
+    for cert in chain {
+        builder.add_extra_chain_cert(cert).map_err(tls_error)?;
+    }
+    builder.set_private_key(&key).map_err(tls_error)?;
+    builder.check_private_key().map_err(tls_error)?;
+    configure_client_auth(&mut builder, &ssl.client_auth)?;
+    configure_protocols(&mut builder, ssl)?;
+    Ok(builder)
+}
+
+/// A certificate chain (leaf first) and its private key.
+pub(super) struct KeyMaterial {
+    chain: Vec<X509>,
+    key: PKey<Private>,
+}
+
+impl KeyMaterial {
+    /// The DER encodings of the certificate chain and the PKCS#8 private key.
+    #[cfg(feature = "rustls")]
+    pub(super) fn to_der(&self) -> AtResult<(Vec<Vec<u8>>, Vec<u8>)> {
+        let chain = self.chain.iter()
+            .map(|cert| cert.to_der())
+            .collect::<Result<_, _>>()
+            .map_err(tls_error)?;
+        Ok((chain, self.key.private_key_to_pkcs8().map_err(tls_error)?))
+    }
+}
+
+/// Read the key material in `files`, decrypting it with their passphrase
+/// if necessary, and check that the private key matches the certificate.
+pub(super) fn load_key_material(files: KeyFiles) -> AtResult<KeyMaterial> {
+    // NOTE: Always passing a passphrase, even an empty one, keeps OpenSSL
+    //       from prompting for one on the terminal.
+    let passphrase = files.passphrase()?.unwrap_or_default();
+    let (chain, key) = match files.pkcs12 {
+        Some(path) => {
+            let invalid = || AtError::InvalidCertificate(path.to_path_buf());
+            let pkcs12 = Pkcs12::from_der(&super::read_file(path)?).map_err(|_| invalid())?;
+            let parsed = pkcs12.parse2(&passphrase)
+                .map_err(|_| AtError::WrongPassphrase(path.to_path_buf()))?;
+            let leaf = parsed.cert.ok_or_else(invalid)?;
+            let key = parsed.pkey
+                .ok_or_else(|| AtError::InvalidPrivateKey(path.to_path_buf()))?;
+            let chain = std::iter::once(leaf)
+                .chain(parsed.ca.into_iter().flatten())
+                .collect();
+            (chain, key)
+        },
+        None => {
+            let invalid = || AtError::InvalidCertificate(files.certificate.to_path_buf());
+            let chain = X509::stack_from_pem(&super::read_file(files.certificate)?)
+                .map_err(|_| invalid())?;
+            if chain.is_empty() { return Err(invalid()); }
+            let key_pem = super::read_file(files.private_key)?;
+            let key = PKey::private_key_from_pem_passphrase(&key_pem, passphrase.as_bytes())
+                .map_err(|_| if super::is_encrypted_pem(&key_pem) {
+                    AtError::WrongPassphrase(files.private_key.to_path_buf())
+                } else {
+                    AtError::InvalidPrivateKey(files.private_key.to_path_buf())
+                })?;
+            (chain, key)
+        },
+    };
+    if !chain[0].public_key().map_err(tls_error)?.public_eq(&key) {
+        return Err(files.key_mismatch());
+    }
+    Ok(KeyMaterial { chain, key })
+}
+
+/// Configure the protocol versions, cipher suites and ALPN protocols
+/// that `builder` accepts.
+///
+/// NOTE: `HttpServer::bind_openssl()` overwrites the ALPN callback of the
+///       builder it is given.  The callback set here still takes effect
+///       because the SNI callback switches every connection over to a
+///       context built by this function.
+fn configure_protocols(builder: &mut SslAcceptorBuilder, ssl: &Ssl) -> AtResult<()> {
+    let versions = super::protocol_versions(ssl)?;
+    let ssl_version = |version: &TlsVersion| match version {
+        TlsVersion::Tls1_2 => SslVersion::TLS1_2,
+        TlsVersion::Tls1_3 => SslVersion::TLS1_3,
+    };
+    builder.set_min_proto_version(versions.first().map(ssl_version))
+        .map_err(tls_error)?;
+    builder.set_max_proto_version(versions.last().map(ssl_version))
+        .map_err(tls_error)?;
+    if !ssl.cipher_suites.is_empty() {
+        let names = |version| ssl.cipher_suites.iter()
+            .filter(|suite| suite.version() == version)
+            .map(|&suite| openssl_cipher_name(suite))
+            .collect::<Vec<_>>()
+            .join(":");
+        // A version without any listed cipher suites was excluded above:
+        if versions.contains(&TlsVersion::Tls1_2) {
+            builder.set_cipher_list(&names(TlsVersion::Tls1_2)).map_err(tls_error)?;
+        }
+        if versions.contains(&TlsVersion::Tls1_3) {
+            builder.set_ciphersuites(&names(TlsVersion::Tls1_3)).map_err(tls_error)?;
+        }
+    }
+    let protocols = super::alpn_protocols(ssl)?;
+    if !protocols.is_empty() {
+        builder.set_alpn_select_callback(move |_, client_protocols| {
+            select_alpn_protocol(&protocols, client_protocols).ok_or(AlpnError::NOACK)
+        });
+    }
+    Ok(())
+}
+
+/// Select the first of the `server` protocols that also occurs in
+/// `client`, which holds the protocols in the ALPN wire format.
+fn select_alpn_protocol<'c>(server: &[Vec<u8>], client: &'c [u8]) -> Option<&'c [u8]> {
+    let mut client_protocols = vec![];
+    let mut rest = client;
+    while let Some((&len, tail)) = rest.split_first() {
+        if tail.len() < len as usize { break; }
+        let (protocol, tail) = tail.split_at(len as usize);
+        client_protocols.push(protocol);
+        rest = tail;
+    }
+    server.iter().find_map(|protocol| {
+        client_protocols.iter().copied().find(|candidate| *candidate == &protocol[..])
+    })
+}
+
+/// The name OpenSSL uses for `suite`.
+fn openssl_cipher_name(suite: CipherSuite) -> &'static str {
+    match suite {
+        CipherSuite::TlsEcdheEcdsaWithAes128GcmSha256 => "ECDHE-ECDSA-AES128-GCM-SHA256",
+        CipherSuite::TlsEcdheEcdsaWithAes256GcmSha384 => "ECDHE-ECDSA-AES256-GCM-SHA384",
+        CipherSuite::TlsEcdheEcdsaWithChacha20Poly1305Sha256 => "ECDHE-ECDSA-CHACHA20-POLY1305",
+        CipherSuite::TlsEcdheRsaWithAes128GcmSha256 => "ECDHE-RSA-AES128-GCM-SHA256",
+        CipherSuite::TlsEcdheRsaWithAes256GcmSha384 => "ECDHE-RSA-AES256-GCM-SHA384",
+        CipherSuite::TlsEcdheRsaWithChacha20Poly1305Sha256 => "ECDHE-RSA-CHACHA20-POLY1305",
+        // OpenSSL uses the IANA names for TLS 1.3 cipher suites:
+        suite => suite.name(),
+    }
+}
+
+/// Configure `builder` to request and verify client certificates,
+/// trusting only the CA certificates listed in `client_auth`.
+fn configure_client_auth(
+    builder: &mut SslAcceptorBuilder,
+    client_auth: &ClientAuth,
+) -> AtResult<()> {
+    let mode = match client_auth.mode {
+        ClientAuthMode::None     => return Ok(()),
+        ClientAuthMode::Optional => SslVerifyMode::PEER,
+        ClientAuthMode::Required => SslVerifyMode::PEER | SslVerifyMode::FAIL_IF_NO_PEER_CERT,
+    };
+    for path in super::client_ca_certificates(client_auth)? {
+        let invalid = || AtError::InvalidCertificate(path.clone());
+        let certs = X509::stack_from_pem(&super::read_file(path)?).map_err(|_| invalid())?;
+        if certs.is_empty() { return Err(invalid()); }
+        for cert in certs {
+            builder.add_client_ca(&cert).map_err(tls_error)?;
+            builder.cert_store_mut().add_cert(cert).map_err(tls_error)?;
+        }
+    }
+    builder.set_verify(mode);
+    // Required for session resumption once client certificates are verified:
+    builder.set_session_id_context(b"actix-settings").map_err(tls_error)?;
+    Ok(())
+}
+
+fn tls_error(err: ErrorStack) -> AtError {
+    AtError::TlsError(err.to_string())
+}
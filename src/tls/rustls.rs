@@ -0,0 +1,217 @@
+//! The `rustls` TLS backend
+
+use crate::actix::{CipherSuite, ClientAuth, ClientAuthMode, Ssl, TlsVersion};
+use crate::error::{AtError, AtResult};
+use crate::tls::{KeyFiles, Reloading, SniMap};
+use rustls::sign::{CertifiedKey, SigningKey};
+use rustls::{
+    AllowAnyAnonymousOrAuthenticatedClient, AllowAnyAuthenticatedClient,
+    Certificate, ClientCertVerified, ClientCertVerifier, ClientHello,
+    DistinguishedNames, NoClientAuth, PrivateKey, ProtocolVersion,
+    ResolvesServerCert, RootCertStore, ServerConfig, SignatureScheme,
+    SupportedCipherSuite, TLSError,
+};
+use std::path::Path;
+use std::sync::Arc;
+use webpki::DNSName;
+
+
+/// Build a `rustls` server configuration from the certificate chains
+/// and private key files named by `ssl`.
+pub fn rustls_server_config(ssl: &Ssl) -> AtResult<ServerConfig> {
+    let versions = super::protocol_versions(ssl)?;
+    let protocols = super::alpn_protocols(ssl)?;
+    // NOTE: actix-web replaces the ALPN protocols of a `rustls` config
+    //       with exactly these, so rather than silently ignoring other
+    //       protocols, they are rejected.
+    if !protocols.is_empty() && protocols != [&b"h2"[..], &b"http/1.1"[..]] {
+        return Err(InvalidValue! {
+            expected: "an `alpn` list that's either empty or [\"h2\", \"http/1.1\"] \
+                       (actix-web doesn't support other ALPN protocols with rustls)",
+            got: format!("{:?}", ssl.alpn),
+        });
+    }
+    let loaded = Reloading::load(ssl, |ssl| Ok(Loaded {
+        certificates: SniMap::load(ssl, certified_key)?,
+        client_verifier: client_cert_verifier(&ssl.client_auth)?,
+    }))?;
+    let mut config = ServerConfig::new(Arc::new(ReloadingVerifier(Arc::clone(&loaded))));
+    config.cert_resolver = Arc::new(SniResolver(loaded));
+    config.versions = versions.into_iter()
+        .rev() // i.e. in order of preference
+        .map(|version| match version {
+            TlsVersion::Tls1_2 => ProtocolVersion::TLSv1_2,
+            TlsVersion::Tls1_3 => ProtocolVersion::TLSv1_3,
+        })
+        .collect();
+    if !ssl.cipher_suites.is_empty() {
+        config.ciphersuites = ssl.cipher_suites.iter()
+            .map(|&suite| supported_cipher_suite(suite))
+            .collect();
+    }
+    config.set_protocols(&protocols);
+    Ok(config)
+}
+
+fn supported_cipher_suite(suite: CipherSuite) -> &'static SupportedCipherSuite {
+    use rustls::ciphersuite::*;
+    match suite {
+        CipherSuite::TlsAes128GcmSha256 => &TLS13_AES_128_GCM_SHA256,
+        CipherSuite::TlsAes256GcmSha384 => &TLS13_AES_256_GCM_SHA384,
+        CipherSuite::TlsChacha20Poly1305Sha256 => &TLS13_CHACHA20_POLY1305_SHA256,
+        CipherSuite::TlsEcdheEcdsaWithAes128GcmSha256 =>
+            &TLS_ECDHE_ECDSA_WITH_AES_128_GCM_SHA256,
+        CipherSuite::TlsEcdheEcdsaWithAes256GcmSha384 =>
+            &TLS_ECDHE_ECDSA_WITH_AES_256_GCM_SHA384,
+        CipherSuite::TlsEcdheEcdsaWithChacha20Poly1305Sha256 =>
+            &TLS_ECDHE_ECDSA_WITH_CHACHA20_POLY1305_SHA256,
+        CipherSuite::TlsEcdheRsaWithAes128GcmSha256 =>
+            &TLS_ECDHE_RSA_WITH_AES_128_GCM_SHA256,
+        CipherSuite::TlsEcdheRsaWithAes256GcmSha384 =>
+            &TLS_ECDHE_RSA_WITH_AES_256_GCM_SHA384,
+        CipherSuite::TlsEcdheRsaWithChacha20Poly1305Sha256 =>
+            &TLS_ECDHE_RSA_WITH_CHACHA20_POLY1305_SHA256,
+    }
+}
+
+/// The certificates and client certificate verifier built from an `Ssl`
+/// table, which are reloaded together.
+struct Loaded {
+    certificates: SniMap<CertifiedKey>,
+    client_verifier: Arc<dyn ClientCertVerifier>,
+}
+
+/// Selects the certificate to serve based on the SNI extension of the
+/// `ClientHello` message.
+struct SniResolver(Arc<Reloading<Loaded>>);
+
+impl ResolvesServerCert for SniResolver {
+    fn resolve(&self, client_hello: ClientHello) -> Option<CertifiedKey> {
+        let server_name = client_hello.server_name().map(Into::into);
+        Some(self.0.current().certificates.resolve(server_name).clone())
+    }
+}
+
+/// Verifies client certificates with the most recently loaded verifier.
+struct ReloadingVerifier(Arc<Reloading<Loaded>>);
+
+impl ClientCertVerifier for ReloadingVerifier {
+    fn offer_client_auth(&self) -> bool {
+        self.0.current().client_verifier.offer_client_auth()
+    }
+
+    fn client_auth_mandatory(&self, sni: Option<&DNSName>) -> Option<bool> {
+        self.0.current().client_verifier.client_auth_mandatory(sni)
+    }
+
+    fn client_auth_root_subjects(&self, sni: Option<&DNSName>) -> Option<DistinguishedNames> {
+        self.0.current().client_verifier.client_auth_root_subjects(sni)
+    }
+
+    fn verify_client_cert(&self, presented_certs: &[Certificate], sni: Option<&DNSName>)
+        -> Result<ClientCertVerified, TLSError>
+    {
+        self.0.current().client_verifier.verify_client_cert(presented_certs, sni)
+    }
+}
+
+/// Load a certificate chain together with its private key.
+fn certified_key(files: KeyFiles) -> AtResult<CertifiedKey> {
+    let (certs, key) = if files.pkcs12.is_some() || files.passphrase.is_some() {
+        decrypt_key_material(files)?
+    } else {
+        (read_certificates(files.certificate)?, read_private_key(files.private_key)?)
+    };
+    let signing_key = rustls::sign::any_supported_type(&key).map_err(|_| {
+        AtError::InvalidPrivateKey(files.pkcs12.unwrap_or(files.private_key).to_path_buf())
+    })?;
+    check_key_matches(files, &certs[0], &*signing_key)?;
+    Ok(CertifiedKey::new(certs, Arc::new(signing_key)))
+}
+
+/// Read PKCS#12 bundles and encrypted private keys, which `rustls` itself
+/// doesn't support, using OpenSSL.
+#[cfg(feature = "openssl")]
+fn decrypt_key_material(files: KeyFiles) -> AtResult<(Vec<Certificate>, PrivateKey)> {
+    let (chain, key) = super::openssl::load_key_material(files)?.to_der()?;
+    Ok((chain.into_iter().map(Certificate).collect(), PrivateKey(key)))
+}
+
+#[cfg(not(feature = "openssl"))]
+fn decrypt_key_material(files: KeyFiles) -> AtResult<(Vec<Certificate>, PrivateKey)> {
+    Err(InvalidValue! {
+        expected: "neither `pkcs12` nor `private-key-passphrase` \
+                   (reading these requires the `openssl` cargo feature)",
+        got: files.pkcs12.unwrap_or(files.private_key).display(),
+    })
+}
+
+/// Check that `key` belongs to `leaf` by verifying a signature made with
+/// `key` against the public key in `leaf`.
+fn check_key_matches(files: KeyFiles, leaf: &Certificate, key: &dyn SigningKey) -> AtResult<()> {
+    let algorithms: &[(SignatureScheme, &webpki::SignatureAlgorithm)] = &[
+        (SignatureScheme::ECDSA_NISTP256_SHA256, &webpki::ECDSA_P256_SHA256),
+        (SignatureScheme::ECDSA_NISTP384_SHA384, &webpki::ECDSA_P384_SHA384),
+        (SignatureScheme::ED25519, &webpki::ED25519),
+        (SignatureScheme::RSA_PSS_SHA256, &webpki::RSA_PSS_2048_8192_SHA256_LEGACY_KEY),
+    ];
+    let schemes: Vec<_> = algorithms.iter().map(|&(scheme, _)| scheme).collect();
+    let signer = key.choose_scheme(&schemes).ok_or_else(|| files.key_mismatch())?;
+    let algorithm = algorithms.iter()
+        .find(|&&(scheme, _)| scheme == signer.get_scheme())
+        .map(|&(_, algorithm)| algorithm)
+        .ok_or_else(|| files.key_mismatch())?;
+    let cert = webpki::EndEntityCert::from(&leaf.0).map_err(|_| {
+        AtError::InvalidCertificate(files.pkcs12.unwrap_or(files.certificate).to_path_buf())
+    })?;
+    let message = b"actix-settings private key check";
+    let signature = signer.sign(message).map_err(|_| files.key_mismatch())?;
+    cert.verify_signature(algorithm, message, &signature)
+        .map_err(|_| files.key_mismatch())
+}
+
+/// Build the verifier for client certificates, trusting only the
+/// CA certificates listed in `client_auth`.
+fn client_cert_verifier(client_auth: &ClientAuth) -> AtResult<Arc<dyn ClientCertVerifier>> {
+    if client_auth.mode == ClientAuthMode::None {
+        return Ok(NoClientAuth::new());
+    }
+    let mut roots = RootCertStore::empty();
+    for path in super::client_ca_certificates(client_auth)? {
+        for cert in read_certificates(path)? {
+            roots.add(&cert)
+                .map_err(|_| AtError::InvalidCertificate(path.clone()))?;
+        }
+    }
+    Ok(match client_auth.mode {
+        ClientAuthMode::None     => unreachable!(),
+        ClientAuthMode::Optional => AllowAnyAnonymousOrAuthenticatedClient::new(roots),
+        ClientAuthMode::Required => AllowAnyAuthenticatedClient::new(roots),
+    })
+}
+
+/// Read all PEM-encoded certificates in the file located at `path`.
+fn read_certificates(path: &Path) -> AtResult<Vec<Certificate>> {
+    let pem = super::read_file(path)?;
+    match rustls::internal::pemfile::certs(&mut &pem[..]) {
+        Ok(certs) if !certs.is_empty() => Ok(certs),
+        _ => Err(AtError::InvalidCertificate(path.to_path_buf())),
+    }
+}
+
+/// Read the first PEM-encoded private key in the file located at `path`.
+/// Both PKCS#8 and RSA (PKCS#1) keys are supported, as long as they
+/// aren't encrypted.
+fn read_private_key(path: &Path) -> AtResult<PrivateKey> {
+    use rustls::internal::pemfile::{pkcs8_private_keys, rsa_private_keys};
+    let invalid = || AtError::InvalidPrivateKey(path.to_path_buf());
+    let pem = super::read_file(path)?;
+    if super::is_encrypted_pem(&pem) {
+        return Err(AtError::WrongPassphrase(path.to_path_buf()));
+    }
+    let mut keys = pkcs8_private_keys(&mut &pem[..]).map_err(|_| invalid())?;
+    if keys.is_empty() {
+        keys = rsa_private_keys(&mut &pem[..]).map_err(|_| invalid())?;
+    }
+    keys.into_iter().next().ok_or_else(invalid)
+}
@@ -0,0 +1,193 @@
+//! Unix domain socket support for `ApplySettings`
+
+use crate::actix::{Listener, SocketOwner, StaleSocket};
+use crate::error::{AtError, AtResult};
+use std::ffi::CString;
+use std::fs::{self, Permissions};
+use std::io;
+use std::os::unix::fs::{FileTypeExt, PermissionsExt};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+
+/// Bind a Unix domain socket at `path`, after dealing with an existing
+/// socket file as configured by `listener.stale_socket`.  The `mode` and
+/// `owner` of `listener` are applied to the new socket file.
+///
+/// NOTE: `HttpServer::bind_uds()` unconditionally removes any file at
+///       `path`, so the socket is bound here and passed to
+///       `HttpServer::listen_uds()` instead.  actix-server also removes the
+///       file at the address of a Unix socket whenever it stops accepting
+///       connections on it, e.g. while its workers are starting or under
+///       backpressure.  So the socket is bound at a private path, which is
+///       hard-linked to `path` and then removed, leaving actix-server an
+///       address that doesn't exist anymore.
+pub(crate) fn bind(listener: &Listener, path: &Path) -> AtResult<UnixListener> {
+    let bind_error = |error: io::Error| AtError::BindError {
+        address: listener.address.clone(),
+        error: error.into(),
+    };
+    // Resolve the owner first, so that a typo doesn't leave a socket behind:
+    let owner = listener.owner.as_ref().map(owner_ids).transpose()?;
+    remove_stale_socket(path, listener.stale_socket).map_err(bind_error)?;
+    let private = private_path(path);
+    let _ = fs::remove_file(&private);
+    let socket = UnixListener::bind(&private).map_err(bind_error)?;
+    let published = (|| {
+        if let Some(mode) = listener.mode {
+            fs::set_permissions(&private, Permissions::from_mode(mode.0))?;
+        }
+        if let Some((uid, gid)) = owner {
+            std::os::unix::fs::chown(&private, uid, gid)?;
+        }
+        // Unlike binding, linking doesn't fail with `AddrInUse`:
+        fs::hard_link(&private, path).map_err(|error| match error.kind() {
+            io::ErrorKind::AlreadyExists => io::ErrorKind::AddrInUse.into(),
+            _ => error,
+        })
+    })();
+    let _ = fs::remove_file(&private);
+    published.map_err(bind_error)?;
+    Ok(socket)
+}
+
+/// A path next to `path` that is unique to this call, at which a socket is
+/// bound before it's linked to `path`.
+fn private_path(path: &Path) -> PathBuf {
+    static COUNT: AtomicUsize = AtomicUsize::new(0);
+    let file_name = path.file_name().map(|name| name.to_string_lossy()).unwrap_or_default();
+    path.with_file_name(format!(
+        ".{}.bind-{}-{}", file_name, std::process::id(), COUNT.fetch_add(1, Ordering::Relaxed)
+    ))
+}
+
+/// Check whether a Unix domain socket could be bound at `path`, without
+/// touching an existing socket file there: It must either not exist, or be
+/// a socket that `listener.stale_socket` allows to be removed.  A socket is
+/// briefly bound next to `path`, to check that its directory is writable.
+pub(crate) fn preflight(listener: &Listener, path: &Path) -> AtResult<io::Result<()>> {
+    listener.owner.as_ref().map(owner_ids).transpose()?;
+    Ok(check_bindable(path, listener.stale_socket))
+}
+
+fn check_bindable(path: &Path, policy: StaleSocket) -> io::Result<()> {
+    match fs::symlink_metadata(path) {
+        Err(err) if err.kind() == io::ErrorKind::NotFound => {},
+        Err(err) => return Err(err),
+        Ok(metadata) if !metadata.file_type().is_socket() =>
+            return Err(io::ErrorKind::AlreadyExists.into()),
+        Ok(_) => match (policy, UnixStream::connect(path)) {
+            (StaleSocket::Force, _) => {},
+            (StaleSocket::Keep, _) | (_, Ok(_)) => return Err(io::ErrorKind::AddrInUse.into()),
+            (StaleSocket::Remove, Err(err))
+                if err.kind() == io::ErrorKind::ConnectionRefused => {},
+            (StaleSocket::Remove, Err(err)) => return Err(err),
+        },
+    }
+    let file_name = path.file_name().map(|name| name.to_string_lossy()).unwrap_or_default();
+    let probe = path.with_file_name(
+        format!(".{}.preflight-{}", file_name, std::process::id())
+    );
+    let _ = fs::remove_file(&probe);
+    UnixListener::bind(&probe)?;
+    fs::remove_file(&probe)
+}
+
+/// Remove the socket file at `path` if `policy` allows it.  Other kinds
+/// of files are left alone, so that binding fails instead.
+fn remove_stale_socket(path: &Path, policy: StaleSocket) -> io::Result<()> {
+    let metadata = match fs::symlink_metadata(path) {
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(()),
+        Err(err) => return Err(err),
+        Ok(metadata) => metadata,
+    };
+    if !metadata.file_type().is_socket() { return Ok(()); }
+    match policy {
+        StaleSocket::Keep => Ok(()),
+        StaleSocket::Force => fs::remove_file(path),
+        StaleSocket::Remove => match UnixStream::connect(path) {
+            // Nobody is accepting connections, so the socket is stale:
+            Err(err) if err.kind() == io::ErrorKind::ConnectionRefused =>
+                fs::remove_file(path),
+            _ => Ok(()),
+        },
+    }
+}
+
+fn owner_ids(owner: &SocketOwner) -> AtResult<(Option<u32>, Option<u32>)> {
+    let uid = owner.user.as_deref().map(user_id).transpose()?;
+    let gid = owner.group.as_deref().map(group_id).transpose()?;
+    Ok((uid, gid))
+}
+
+/// Look up the id of the user called `name`.  Numeric ids are returned
+/// as-is.
+pub(crate) fn user_id(name: &str) -> AtResult<u32> {
+    if let Ok(uid) = name.parse() { return Ok(uid); }
+    let unknown = || AtError::UnknownUser(name.to_string());
+    let c_name = CString::new(name).map_err(|_| unknown())?;
+    lookup(|entry: &mut libc::passwd, buf, result| unsafe {
+        libc::getpwnam_r(c_name.as_ptr(), entry, buf.as_mut_ptr(), buf.len(), result)
+    }, |entry| entry.pw_uid)
+        .ok_or_else(unknown)
+}
+
+/// Look up the id of the primary group of the user called `name`, which
+/// may also be a numeric user id.
+pub(crate) fn user_group_id(name: &str) -> AtResult<u32> {
+    let unknown = || AtError::UnknownUser(name.to_string());
+    match name.parse::<libc::uid_t>() {
+        Ok(uid) => lookup(|entry: &mut libc::passwd, buf, result| unsafe {
+            libc::getpwuid_r(uid, entry, buf.as_mut_ptr(), buf.len(), result)
+        }, |entry| entry.pw_gid),
+        Err(_) => {
+            let c_name = CString::new(name).map_err(|_| unknown())?;
+            lookup(|entry: &mut libc::passwd, buf, result| unsafe {
+                libc::getpwnam_r(c_name.as_ptr(), entry, buf.as_mut_ptr(), buf.len(), result)
+            }, |entry| entry.pw_gid)
+        },
+    }
+        .ok_or_else(unknown)
+}
+
+/// Look up the id of the group called `name`.  Numeric ids are returned
+/// as-is.
+pub(crate) fn group_id(name: &str) -> AtResult<u32> {
+    if let Ok(gid) = name.parse() { return Ok(gid); }
+    let unknown = || AtError::UnknownGroup(name.to_string());
+    let c_name = CString::new(name).map_err(|_| unknown())?;
+    lookup(|entry: &mut libc::group, buf, result| unsafe {
+        libc::getgrnam_r(c_name.as_ptr(), entry, buf.as_mut_ptr(), buf.len(), result)
+    }, |entry| entry.gr_gid)
+        .ok_or_else(unknown)
+}
+
+/// The entries that `lookup()` can be used for.
+///
+/// # Safety
+///
+/// Only implement this for plain C structs, for which all zeroes is a valid
+/// value.
+unsafe trait Entry {}
+
+unsafe impl Entry for libc::passwd {}
+unsafe impl Entry for libc::group {}
+
+/// Call a reentrant `get*nam_r()` function, growing the buffer for its
+/// strings until they fit, and return the `id` of the entry it found.
+/// Returns `None` if there's no such entry.
+fn lookup<T: Entry, F>(mut get: F, id: fn(&T) -> u32) -> Option<u32>
+where F: FnMut(&mut T, &mut [libc::c_char], &mut *mut T) -> libc::c_int {
+    let mut buf = vec![0; 1024];
+    loop {
+        // SAFETY: `T: Entry`, so all zeroes is a valid value.
+        let mut entry: T = unsafe { std::mem::zeroed() };
+        let mut result = std::ptr::null_mut();
+        match get(&mut entry, &mut buf, &mut result) {
+            libc::ERANGE if buf.len() < 1 << 20 => buf.resize(buf.len() * 2, 0),
+            0 if !result.is_null() => return Some(id(&entry)),
+            _ => return None,
+        }
+    }
+}